@@ -1,4 +1,5 @@
-use clap::{arg, Parser};
+use clap::{arg, Args, Parser, Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
 
 #[derive(Parser, Debug)]
 pub struct BalanceArgs {
@@ -7,6 +8,15 @@ pub struct BalanceArgs {
         help = "The account address to fetch the balance of."
     )]
     pub address: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        help = "Keep the display updated, redrawing on changes and at least every SECONDS. Defaults to 5.",
+        num_args = 0..=1,
+        default_missing_value = "5"
+    )]
+    pub watch: Option<u64>,
 }
 
 #[derive(Parser, Debug)]
@@ -15,10 +25,35 @@ pub struct BenchmarkArgs {
         long,
         short,
         value_name = "THREAD_COUNT",
-        help = "The number of cores to use during the benchmark",
+        help = "The number of cores to use during the benchmark. Pass a list, e.g. 1,2,4,8, to sweep.",
+        value_delimiter = ',',
         default_value = "1"
     )]
-    pub cores: u64,
+    pub cores: Vec<u64>,
+
+    #[arg(
+        long,
+        short,
+        value_name = "SECONDS",
+        help = "The number of seconds to run each benchmark trial for. Defaults to 30, or 10 when sweeping."
+    )]
+    pub duration: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Sweep core counts, pinning and SMT options, measure thermal falloff on the fastest setup over a sustained run, then save it for mining."
+    )]
+    pub sweep: bool,
+
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        help = "The format to print results in. Defaults to text."
+    )]
+    pub format: Option<OutputFormat>,
+
+    #[command(flatten)]
+    pub threads: ThreadArgs,
 }
 
 #[derive(Parser, Debug)]
@@ -46,6 +81,9 @@ pub struct ClaimArgs {
         help = "The optional pool url to claim rewards from."
     )]
     pub pool_url: Option<String>,
+
+    #[command(flatten)]
+    pub offline: OfflineArgs,
 }
 
 #[derive(Parser, Debug)]
@@ -54,20 +92,155 @@ pub struct CloseArgs {}
 #[derive(Parser, Debug)]
 pub struct ConfigArgs {}
 
-#[cfg(feature = "admin")]
 #[derive(Parser, Debug)]
-pub struct InitializeArgs {}
+pub struct ConfigFileArgs {
+    #[command(subcommand)]
+    pub command: ConfigFileCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigFileCommand {
+    #[command(about = "Write a template ore config file")]
+    Init(ConfigFileInitArgs),
+
+    #[command(about = "Print the resolved settings of the active profile")]
+    Show(ConfigFileShowArgs),
+}
 
 #[derive(Parser, Debug)]
-pub struct MineArgs {
+pub struct ConfigFileInitArgs {
+    #[arg(long, help = "Overwrite an existing config file.")]
+    pub force: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct ConfigFileShowArgs {}
+
+#[derive(Parser, Debug)]
+pub struct CtlArgs {
     #[arg(
         long,
-        short,
+        value_name = "SOCKET_PATH",
+        help = "The control socket of the running miner. Defaults to the control_socket setting.",
+        env = "ORE_CONTROL_SOCKET"
+    )]
+    pub socket: Option<String>,
+
+    #[command(subcommand)]
+    pub command: CtlCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CtlCommand {
+    #[command(about = "Print the settings and session stats of the miner")]
+    Status,
+
+    #[command(about = "Pause hashing")]
+    Pause,
+
+    #[command(about = "Resume hashing")]
+    Resume,
+
+    #[command(about = "Change the number of cores to hash with from the next round on")]
+    Cores(CtlCoresArgs),
+
+    #[command(about = "Change the number of seconds before the deadline to stop hashing")]
+    BufferTime(CtlBufferTimeArgs),
+
+    #[command(about = "Keep hashing each round until a difficulty is reached")]
+    Difficulty(CtlDifficultyArgs),
+
+    #[command(about = "Change how Jito tips are picked")]
+    Tip(CtlTipArgs),
+
+    #[command(about = "Claim the signer's rewards")]
+    Claim(CtlClaimArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct CtlCoresArgs {
+    #[arg(
         value_name = "CORES_COUNT",
-        help = "The number of CPU cores to allocate to mining.",
-        default_value = "1"
+        help = "The number of CPU cores to mine with."
     )]
     pub cores: u64,
+}
+
+#[derive(Parser, Debug)]
+pub struct CtlBufferTimeArgs {
+    #[arg(
+        value_name = "SECONDS",
+        help = "The number seconds before the deadline to stop mining and start submitting."
+    )]
+    pub seconds: u64,
+}
+
+#[derive(Parser, Debug)]
+pub struct CtlDifficultyArgs {
+    #[arg(
+        value_name = "DIFFICULTY",
+        help = "The difficulty to reach before submitting. Omit to submit at the deadline again."
+    )]
+    pub difficulty: Option<u32>,
+}
+
+#[derive(Parser, Debug)]
+pub struct CtlTipArgs {
+    #[arg(
+        long,
+        value_name = "PERCENTILE",
+        help = "The landed tip percentile to pay, one of 25, 50, 75, 95 or 99.",
+        value_parser = crate::jito::parse_tip_percentile
+    )]
+    pub percentile: Option<u8>,
+
+    #[arg(long, value_name = "LAMPORTS", help = "The maximum Jito tip to pay.")]
+    pub max_tip: Option<u64>,
+}
+
+#[derive(Parser, Debug)]
+pub struct CtlClaimArgs {
+    #[arg(
+        value_name = "AMOUNT",
+        help = "The amount of rewards to claim. Defaults to max."
+    )]
+    pub amount: Option<f64>,
+
+    #[arg(
+        long,
+        value_name = "WALLET_ADDRESS",
+        help = "Wallet address to receive claimed tokens. Defaults to the signer."
+    )]
+    pub to: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct EstimateArgs {
+    #[arg(
+        long,
+        value_name = "HASHES_PER_SEC",
+        help = "Your hashrate in hashes per second.",
+        required_unless_present = "benchmark"
+    )]
+    pub hashrate: Option<f64>,
+
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        help = "Measure your hashrate with a benchmark of this many seconds instead.",
+        num_args = 0..=1,
+        default_missing_value = "10",
+        value_parser = clap::value_parser!(u64).range(1..),
+        conflicts_with = "hashrate"
+    )]
+    pub benchmark: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "ORE",
+        help = "The stake to estimate with. Defaults to the balance of the pool proof that ore mine submits to."
+    )]
+    pub stake: Option<f64>,
 
     #[arg(
         long,
@@ -78,24 +251,290 @@ pub struct MineArgs {
     )]
     pub buffer_time: u64,
 
+    #[arg(
+        long,
+        value_name = "MICROLAMPORTS",
+        help = "The priority fee per compute unit paid on each submission. Defaults to the mining fee."
+    )]
+    pub priority_fee: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "LAMPORTS",
+        help = "The Jito tip paid on each submission. Defaults to the live tip when --jito is set."
+    )]
+    pub jito_tip: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "SOL",
+        help = "The price of one ORE in SOL, used to report net profit."
+    )]
+    pub ore_price: Option<f64>,
+
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        help = "The format to print results in. Defaults to text."
+    )]
+    pub format: Option<OutputFormat>,
+}
+
+#[derive(Parser, Debug)]
+pub struct FleetArgs {
+    #[arg(
+        long,
+        value_name = "FILEPATH",
+        help = "File listing the authorities to monitor, one address per line. Lines starting with # are ignored."
+    )]
+    pub authorities: String,
+
+    #[arg(
+        long,
+        value_name = "COLUMN",
+        help = "The column to sort by. Defaults to stake."
+    )]
+    pub sort: Option<FleetColumn>,
+
+    #[arg(
+        long,
+        value_name = "MINUTES",
+        help = "Flag authorities as down when their last hash is older than this many minutes.",
+        default_value = "5"
+    )]
+    pub down_after: u64,
+
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        help = "How often to refresh the table.",
+        default_value = "30"
+    )]
+    pub interval: u64,
+
+    #[arg(
+        long,
+        value_name = "FILEPATH",
+        help = "Write the results to a .csv or .json file and exit instead of showing the table."
+    )]
+    pub export: Option<String>,
+}
+
+#[cfg(feature = "admin")]
+#[derive(Parser, Debug)]
+pub struct InitializeArgs {}
+
+#[derive(Parser, Debug)]
+pub struct KeypairArgs {
+    #[command(subcommand)]
+    pub command: KeypairCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum KeypairCommand {
+    #[command(about = "Encrypt a keypair file with a password")]
+    Encrypt(KeypairEncryptArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct KeypairEncryptArgs {
+    #[arg(
+        value_name = "KEYPAIR_FILEPATH",
+        help = "The keypair to encrypt. Defaults to the configured keypair."
+    )]
+    pub keypair: Option<String>,
+
+    #[arg(
+        long,
+        short,
+        value_name = "OUTPUT_FILEPATH",
+        help = "Filepath to write the encrypted keypair to."
+    )]
+    pub out: String,
+}
+
+#[derive(Parser, Debug)]
+pub struct MineArgs {
+    #[arg(
+        long,
+        short,
+        value_name = "CORES_COUNT",
+        help = "The number of CPU cores to allocate to mining. Defaults to the saved benchmark recommendation, or 1.",
+        env = "ORE_CORES"
+    )]
+    pub cores: Option<u64>,
+
+    #[arg(
+        long,
+        short,
+        value_name = "SECONDS",
+        help = "The number seconds before the deadline to stop mining and start submitting. Defaults to 5.",
+        env = "ORE_BUFFER_TIME"
+    )]
+    pub buffer_time: Option<u64>,
+
     #[arg(
         long,
         short,
         value_name = "POOL_URL",
-        help = "The optional pool url to join and forward solutions to."
+        help = "The optional pool url to join and forward solutions to.",
+        env = "ORE_POOL_URL"
     )]
     pub pool_url: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "STRATEGY",
+        help = "How to choose the bus to submit to. Defaults to weighted-random.",
+        env = "ORE_BUS_STRATEGY"
+    )]
+    pub bus_strategy: Option<BusStrategy>,
+
+    #[arg(
+        long,
+        value_name = "AMOUNT",
+        help = "Claim rewards once the stake of the signer's proof reaches this much ORE. Rewards mined through the pool accrue to the pool's proof instead, which the signer cannot claim from.",
+        env = "ORE_AUTO_CLAIM_THRESHOLD"
+    )]
+    pub auto_claim_threshold: Option<f64>,
+
+    #[arg(
+        long,
+        value_name = "WALLET_ADDRESS",
+        help = "Wallet address to receive automatic claims. Defaults to the signer.",
+        env = "ORE_AUTO_CLAIM_TO"
+    )]
+    pub auto_claim_to: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        help = "The minimum number of seconds between automatic claims. Defaults to 0.",
+        env = "ORE_AUTO_CLAIM_INTERVAL"
+    )]
+    pub auto_claim_interval: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "AMOUNT",
+        help = "Stake the ORE in the signer's wallet once it reaches this much, so rewards paid out to the wallet compound.",
+        env = "ORE_AUTO_STAKE_THRESHOLD"
+    )]
+    pub auto_stake_threshold: Option<f64>,
+
+    #[arg(
+        long,
+        help = "Show a full-screen dashboard with per-core hashrate, recent submissions and fees. Press p to pause or resume, +/- to change the core count and q to quit. Defaults to false.",
+        env = "ORE_TUI"
+    )]
+    pub tui: bool,
+
+    #[arg(
+        long,
+        value_name = "SOCKET_PATH",
+        help = "Serve JSON-RPC control requests on this unix socket, for use with `ore ctl`.",
+        env = "ORE_CONTROL_SOCKET"
+    )]
+    pub control_socket: Option<String>,
+
+    #[command(flatten)]
+    pub threads: ThreadArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct NonceArgs {
+    #[command(subcommand)]
+    pub command: NonceCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum NonceCommand {
+    #[command(about = "Create a durable nonce account derived from the signer")]
+    Create(NonceCreateArgs),
+
+    #[command(about = "Print the authority and current value of a nonce account")]
+    Show(NonceShowArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct NonceCreateArgs {
+    #[arg(
+        long,
+        value_name = "SEED",
+        help = "The seed to derive the nonce account address from. Use different seeds for multiple accounts.",
+        default_value = "ore-nonce"
+    )]
+    pub seed: String,
+
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        help = "The authority allowed to advance the nonce. Defaults to the signer."
+    )]
+    pub authority: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct NonceShowArgs {
+    #[arg(
+        value_name = "ADDRESS",
+        help = "The nonce account to show. Defaults to --nonce, or the account created with the default seed."
+    )]
+    pub address: Option<String>,
 }
 
 #[derive(Parser, Debug)]
 pub struct ProofArgs {
     #[arg(value_name = "ADDRESS", help = "The address of the proof to fetch.")]
     pub address: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        help = "Keep the display updated, redrawing on changes and at least every SECONDS. Defaults to 5.",
+        num_args = 0..=1,
+        default_missing_value = "5"
+    )]
+    pub watch: Option<u64>,
 }
 
 #[derive(Parser, Debug)]
 pub struct RewardsArgs {}
 
+#[derive(Parser, Debug)]
+pub struct SignerServerArgs {
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        help = "The address to listen for signing requests on.",
+        default_value = "127.0.0.1:8787"
+    )]
+    pub listen: String,
+
+    #[arg(
+        long,
+        value_name = "MICROLAMPORTS",
+        help = "The highest priority fee per compute unit to sign for. Transactions may request at most 500000 compute units.",
+        default_value = "100000"
+    )]
+    pub max_priority_fee: u64,
+
+    #[arg(
+        long,
+        value_name = "LAMPORTS",
+        help = "The highest Jito tip to sign for. Transactions may include at most one tip.",
+        default_value = "100000"
+    )]
+    pub max_tip: u64,
+
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        help = "Token accounts that claims may pay out to. Defaults to the signer's ORE token account."
+    )]
+    pub beneficiary: Vec<String>,
+}
+
 #[derive(Parser, Debug)]
 pub struct StakeArgs {
     #[arg(
@@ -110,6 +549,18 @@ pub struct StakeArgs {
         help = "Token account to send ORE from. Defaults to the associated token account."
     )]
     pub token_account: Option<String>,
+
+    #[command(flatten)]
+    pub offline: OfflineArgs,
+}
+
+#[derive(Parser, Debug)]
+pub struct SubmitArgs {
+    #[arg(
+        value_name = "FILEPATH",
+        help = "File holding a transaction signed with --sign-only, or - to read it from stdin."
+    )]
+    pub file: String,
 }
 
 #[derive(Parser, Debug)]
@@ -122,6 +573,68 @@ pub struct TransferArgs {
         help = "The account address of the receipient."
     )]
     pub to: String,
+
+    #[command(flatten)]
+    pub offline: OfflineArgs,
+}
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct OfflineArgs {
+    #[arg(
+        long,
+        help = "Sign the transaction without submitting it, and print it for `ore submit`.",
+        requires = "blockhash"
+    )]
+    pub sign_only: bool,
+
+    #[arg(
+        long,
+        value_name = "BLOCKHASH",
+        help = "The blockhash to sign with, or the nonce value with --nonce.",
+        requires = "sign_only"
+    )]
+    pub blockhash: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "FILEPATH",
+        help = "Filepath to write the signed transaction to. Defaults to stdout.",
+        requires = "sign_only"
+    )]
+    pub out: Option<String>,
+}
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct ThreadArgs {
+    #[arg(
+        long,
+        value_name = "CORE_LIST",
+        help = "Explicit list of core ids to hash on, e.g. 0,2,4-7. Overrides --cores.",
+        env = "ORE_CORE_LIST"
+    )]
+    pub core_list: Option<String>,
+
+    #[arg(
+        long,
+        help = "Do not pin hashing threads to cores.",
+        env = "ORE_NO_PIN"
+    )]
+    pub no_pin: bool,
+
+    #[arg(
+        long,
+        help = "Skip SMT siblings and only hash on one thread per physical core.",
+        env = "ORE_NO_SMT"
+    )]
+    pub no_smt: bool,
+
+    #[arg(
+        long,
+        value_name = "THREADS",
+        help = "The number of hashing threads to run on each selected core. Defaults to 1.",
+        env = "ORE_THREADS_PER_CORE"
+    )]
+    pub threads_per_core: Option<u64>,
 }
 
 #[derive(Parser, Debug)]
@@ -132,3 +645,45 @@ pub struct UpgradeArgs {
     )]
     pub amount: Option<f64>,
 }
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JitoRegion {
+    /// Pick the region with the lowest measured latency.
+    Auto,
+    Mainnet,
+    Ny,
+    Amsterdam,
+    Frankfurt,
+    Tokyo,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BusStrategy {
+    /// The bus with the most remaining rewards.
+    MostRewards,
+    /// A random bus, weighted by remaining rewards.
+    WeightedRandom,
+    /// The bus with the oldest recent transaction, to avoid write-lock contention.
+    LeastRecentlyWritten,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FleetColumn {
+    Authority,
+    Stake,
+    Multiplier,
+    /// The time since the last hash.
+    LastHash,
+    Hashes,
+    Rewards,
+}
@@ -0,0 +1,244 @@
+use std::{
+    cell::RefCell,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use colored::*;
+use ore_api::{consts::MINT_ADDRESS, state::Proof};
+use ore_utils::AccountDeserialize;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use spl_associated_token_account::get_associated_token_address;
+
+use crate::{
+    args::MineArgs,
+    config_file::Settings,
+    control::MineControl,
+    dashboard::Dashboard,
+    error::SendError,
+    exit_with_error,
+    utils::{amount_f64_to_u64, amount_u64_to_string, create_token_account_ix, proof_pubkey},
+    Miner,
+};
+
+/// How often the signer's stake and wallet are checked for an automatic claim or stake.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often to check whether mining stopped while waiting for the next check.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// When the mining loop claims the signer's rewards or stakes the signer's ORE on its own.
+pub struct AutoClaim {
+    /// The stake of the signer's proof at which to claim it, if auto-claiming.
+    claim_threshold: Option<u64>,
+    beneficiary: Pubkey,
+    interval: Duration,
+    last_claim_at: Option<Instant>,
+    /// The ORE balance of the signer's wallet at which to stake it, if auto-staking.
+    stake_threshold: Option<u64>,
+}
+
+impl AutoClaim {
+    /// Returns the auto-claim and auto-stake policy from the mine args and settings, or none if
+    /// neither threshold is set. Exits if the policy is invalid.
+    pub fn new(args: &MineArgs, settings: &Settings, signer: Pubkey) -> Option<Self> {
+        let claim_threshold = args
+            .auto_claim_threshold
+            .or(settings.auto_claim_threshold)
+            .map(amount_f64_to_u64);
+        let stake_threshold = args
+            .auto_stake_threshold
+            .or(settings.auto_stake_threshold)
+            .map(amount_f64_to_u64);
+        if claim_threshold.is_none() && stake_threshold.is_none() {
+            return None;
+        }
+        let beneficiary = match args
+            .auto_claim_to
+            .clone()
+            .or(settings.auto_claim_to.clone())
+        {
+            Some(to) => Pubkey::from_str(&to)
+                .unwrap_or_else(|_| exit_with_error(&format!("Invalid wallet address: {}", to))),
+            None => signer,
+        };
+        if claim_threshold.is_some() && stake_threshold.is_some() && beneficiary.eq(&signer) {
+            exit_with_error(
+                "Auto-staking would stake the auto-claimed rewards straight back. Set --auto-claim-to to another wallet.",
+            );
+        }
+        let interval = args
+            .auto_claim_interval
+            .or(settings.auto_claim_interval)
+            .unwrap_or(0);
+        Some(Self {
+            claim_threshold,
+            beneficiary,
+            interval: Duration::from_secs(interval),
+            last_claim_at: None,
+            stake_threshold,
+        })
+    }
+
+    /// Describes the policy for the mining banner.
+    pub fn describe(&self) -> String {
+        let mut lines = vec![];
+        if let Some(threshold) = self.claim_threshold {
+            lines.push(format!(
+                "Auto-claiming the signer's proof above {} ORE to {}",
+                amount_u64_to_string(threshold),
+                self.beneficiary
+            ));
+        }
+        if let Some(threshold) = self.stake_threshold {
+            lines.push(format!(
+                "Auto-staking the signer's wallet above {} ORE",
+                amount_u64_to_string(threshold)
+            ));
+        }
+        lines.join("\n")
+    }
+
+    /// Returns the amount to claim from a proof with the given stake, or none if it is not time
+    /// to claim yet.
+    fn claimable(&self, balance: u64) -> Option<u64> {
+        if balance.lt(&self.claim_threshold?) || balance.eq(&0) {
+            return None;
+        }
+        if let Some(last_claim_at) = self.last_claim_at {
+            if last_claim_at.elapsed().lt(&self.interval) {
+                return None;
+            }
+        }
+        Some(balance)
+    }
+
+    /// Returns the amount to stake from a wallet with the given ORE balance, or none if it is
+    /// below the threshold.
+    fn stakeable(&self, balance: u64) -> Option<u64> {
+        if balance.lt(&self.stake_threshold?) || balance.eq(&0) {
+            return None;
+        }
+        Some(balance)
+    }
+}
+
+impl Miner {
+    /// Checks the signer's stake and wallet, and claims or stakes when due until mining stops.
+    /// Runs alongside the mining loop rather than inside it, so a transaction that takes several
+    /// blockhash lifetimes to land does not hold up submissions. Lines are added to the
+    /// dashboard log if it is open, and otherwise held in `deferred` for the mining loop to print
+    /// between rounds, so they do not draw over the spinner.
+    pub async fn run_auto_claim(
+        &self,
+        auto_claim: &mut AutoClaim,
+        control: &MineControl,
+        dashboard: Option<&Dashboard>,
+        deferred: &RefCell<Vec<String>>,
+    ) {
+        let log = |line: String| match dashboard {
+            Some(dashboard) => dashboard.log(line),
+            None => deferred.borrow_mut().push(line),
+        };
+        while !control.is_shutting_down() {
+            self.auto_claim(auto_claim, &log).await;
+            self.auto_stake(auto_claim, &log).await;
+            let checked_at = Instant::now();
+            while checked_at.elapsed().lt(&CHECK_INTERVAL) && !control.is_shutting_down() {
+                tokio::time::sleep(STOP_POLL_INTERVAL).await;
+            }
+        }
+    }
+
+    /// Claims the signer's rewards once its stake crosses the threshold. Claims pay the same
+    /// capped priority fee and tip as every other transaction.
+    async fn auto_claim(&self, auto_claim: &mut AutoClaim, log: &impl Fn(String)) {
+        if auto_claim.claim_threshold.is_none() {
+            return;
+        }
+        let signer = self.signer().pubkey();
+        let Ok(data) = self
+            .rpc_client
+            .get_account_data(&proof_pubkey(signer))
+            .await
+        else {
+            return;
+        };
+        let Ok(proof) = Proof::try_from_bytes(&data) else {
+            return;
+        };
+        let Some(amount) = auto_claim.claimable(proof.balance) else {
+            return;
+        };
+
+        let beneficiary = auto_claim.beneficiary;
+        auto_claim.last_claim_at = Some(Instant::now());
+        log(match self.send_claim(beneficiary, amount).await {
+            Ok(signature) => format!(
+                "{} {} ORE to {} ({})",
+                "Auto-claimed".bold().green(),
+                amount_u64_to_string(amount),
+                beneficiary,
+                signature
+            ),
+            Err(err) => format!("{} Auto-claim failed: {}", "WARNING".bold().yellow(), err),
+        });
+    }
+
+    /// Stakes the ORE in the signer's wallet into its proof once it crosses the threshold, so
+    /// rewards paid out to the wallet compound.
+    async fn auto_stake(&self, auto_claim: &AutoClaim, log: &impl Fn(String)) {
+        if auto_claim.stake_threshold.is_none() {
+            return;
+        }
+        let signer = self.signer().pubkey();
+        let sender = get_associated_token_address(&signer, &MINT_ADDRESS);
+        let Ok(balance) = self.rpc_client.get_token_account_balance(&sender).await else {
+            return;
+        };
+        let Some(amount) = balance
+            .amount
+            .parse()
+            .ok()
+            .and_then(|balance| auto_claim.stakeable(balance))
+        else {
+            return;
+        };
+
+        let ixs = [ore_api::instruction::stake(signer, sender, amount)];
+        log(match self.send_and_confirm_silently(&ixs).await {
+            Ok(signature) => format!(
+                "{} {} ORE ({})",
+                "Auto-staked".bold().green(),
+                amount_u64_to_string(amount),
+                signature
+            ),
+            Err(err) => format!("{} Auto-stake failed: {}", "WARNING".bold().yellow(), err),
+        });
+    }
+
+    /// Claims the given amount of the signer's rewards to the beneficiary's token account,
+    /// creating it if needed. Sent without a spinner, like every claim made while mining.
+    pub async fn send_claim(
+        &self,
+        beneficiary: Pubkey,
+        amount: u64,
+    ) -> Result<Signature, SendError> {
+        let mut ixs = vec![];
+        ixs.extend(
+            create_token_account_ix(
+                Some(&self.rpc_client),
+                self.fee_payer().pubkey(),
+                beneficiary,
+            )
+            .await,
+        );
+        ixs.push(ore_api::instruction::claim(
+            self.signer().pubkey(),
+            get_associated_token_address(&beneficiary, &MINT_ADDRESS),
+            amount,
+        ));
+        self.send_and_confirm_silently(&ixs).await
+    }
+}
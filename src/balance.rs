@@ -1,11 +1,17 @@
-use std::str::FromStr;
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
+};
 
-use solana_program::pubkey::Pubkey;
-use solana_sdk::signature::Signer;
+use ore_api::{consts::MINT_ADDRESS, state::Proof};
+use ore_utils::AccountDeserialize;
+use solana_program::{program_pack::Pack, pubkey::Pubkey};
+use spl_associated_token_account::get_associated_token_address;
 
 use crate::{
     args::BalanceArgs,
-    utils::{amount_u64_to_string, get_proof_with_authority},
+    utils::{amount_u64_to_string, get_proof_with_authority, proof_pubkey},
+    watch::{amount_change, proof_activity},
     Miner,
 };
 
@@ -22,6 +28,11 @@ impl Miner {
         } else {
             signer.pubkey()
         };
+        if let Some(interval) = args.watch {
+            self.watch_balance(address, Duration::from_secs(interval.max(1)))
+                .await;
+            return;
+        }
         let proof = get_proof_with_authority(&self.rpc_client, address).await;
         let token_account_address = spl_associated_token_account::get_associated_token_address(
             &address,
@@ -42,4 +53,44 @@ impl Miner {
             amount_u64_to_string(proof.balance)
         )
     }
+
+    /// Redraws the wallet balance and stake of the address as they change.
+    async fn watch_balance(&self, address: Pubkey, interval: Duration) {
+        let addresses = [
+            get_associated_token_address(&address, &MINT_ADDRESS),
+            proof_pubkey(address),
+        ];
+        let started = Instant::now();
+        let mut start_tokens = None;
+        let mut start_proof = None;
+        self.watch_accounts(&addresses, interval, |accounts| {
+            let tokens = accounts[0]
+                .as_deref()
+                .and_then(|data| spl_token::state::Account::unpack(data).ok())
+                .map_or(0, |account| account.amount);
+            let start_tokens = *start_tokens.get_or_insert(tokens);
+            let mut display = format!(
+                "Address: {}\nBalance: {} ORE ({} since start)\n",
+                address,
+                amount_u64_to_string(tokens),
+                amount_change(start_tokens, tokens)
+            );
+            let proof = accounts[1]
+                .as_deref()
+                .and_then(|data| Proof::try_from_bytes(data).ok());
+            match proof {
+                Some(proof) => {
+                    let start_proof = start_proof.get_or_insert(*proof);
+                    display.push_str(&format!(
+                        "Stake: {} ORE\n{}",
+                        amount_u64_to_string(proof.balance),
+                        proof_activity(start_proof, proof, started.elapsed())
+                    ));
+                }
+                None => display.push_str("Stake: 0 ORE (no proof account)\n"),
+            }
+            display
+        })
+        .await;
+    }
 }
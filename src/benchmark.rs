@@ -1,80 +1,376 @@
-use std::{sync::Arc, time::Instant};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use drillx::equix;
+use colored::*;
+use serde::Serialize;
 use solana_rpc_client::spinner;
 
-use crate::{args::BenchmarkArgs, Miner};
+use crate::{
+    args::{BenchmarkArgs, OutputFormat, ThreadArgs},
+    config_file::ConfigFile,
+    cores::{core_counts, format_core_list, hash_threads},
+    difficulty::{best_probability, expected_best_difficulty},
+    hash_pool::HashPool,
+    Miner,
+};
 
-const TEST_DURATION: i64 = 30;
+/// The length of a mining round, used to project the expected best difficulty.
+const ROUND_DURATION: f64 = 60.0;
+
+/// The default trial length in seconds.
+const TEST_DURATION: u64 = 30;
+
+/// The default trial length in seconds when sweeping.
+const SWEEP_TEST_DURATION: u64 = 10;
+
+/// The length in seconds of each window of the sustained run on the recommended setup. The
+/// hashrate of the first and last window are compared to measure thermal falloff.
+const SUSTAINED_WINDOW: u64 = 15;
+
+/// The number of windows in the sustained run, long enough for the cores to heat up.
+const SUSTAINED_WINDOWS: u64 = 4;
+
+/// Setups within this fraction of the best hashrate are considered equivalent, and the one
+/// using the fewest threads is recommended.
+const SWEEP_TOLERANCE: f64 = 0.02;
+
+#[derive(Debug, Serialize)]
+pub struct BenchmarkReport {
+    pub threads: usize,
+    pub duration: f64,
+    pub hashes_per_sec: f64,
+    pub nonces_per_sec: f64,
+    pub expected_best_difficulty: f64,
+    pub cores: Vec<CoreReport>,
+    pub difficulties: Vec<DifficultyReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CoreReport {
+    pub core: usize,
+    pub hashes_per_sec: f64,
+    pub nonces_per_sec: f64,
+    pub best_difficulty: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DifficultyReport {
+    pub difficulty: u32,
+    /// The number of hashes observed at this difficulty during the trial.
+    pub observed: u64,
+    /// The probability that a 60s round reaches at least this difficulty.
+    pub round_probability: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SweepTrial {
+    pub cores: u64,
+    pub pin: bool,
+    pub smt: bool,
+    pub core_list: String,
+    pub hashes_per_sec: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SweepReport {
+    pub trials: Vec<SweepTrial>,
+    pub recommended: Option<usize>,
+    /// The fractional hashrate drop between the first and last window of a sustained run on the
+    /// recommended setup.
+    pub thermal_falloff: Option<f64>,
+}
 
 impl Miner {
     pub async fn benchmark(&self, args: BenchmarkArgs) {
-        // Check num threads
-        self.check_num_cores(args.cores);
+        if args.sweep {
+            return self.benchmark_sweep(args).await;
+        }
 
-        // Dispatch job to each thread
-        let challenge = [0; 32];
-        let progress_bar = Arc::new(spinner::new_progress_bar());
+        let duration = args.duration.unwrap_or(TEST_DURATION);
+        let mut reports = vec![];
+        for cores in args.cores.iter().copied() {
+            // Check num threads
+            let threads = hash_threads(cores, &args.threads);
+            self.check_num_cores(threads.len() as u64);
+
+            let pool = HashPool::new(&threads);
+            reports.push(Self::benchmark_pool(&pool, duration).await);
+        }
+
+        let format = args
+            .format
+            .or(self.settings.format)
+            .unwrap_or(OutputFormat::Text);
+        match format {
+            OutputFormat::Json => {
+                println!("{}", serde_json::to_string_pretty(&reports).unwrap());
+            }
+            OutputFormat::Text => {
+                for report in reports.iter() {
+                    print_report(report);
+                }
+            }
+        }
+    }
+
+    /// Runs short trials across core counts, with and without pinning and SMT, checks the
+    /// fastest setup for thermal falloff over a sustained run, and saves it to the config file.
+    async fn benchmark_sweep(&self, args: BenchmarkArgs) {
+        let duration = args.duration.unwrap_or(SWEEP_TEST_DURATION);
+        let format = args
+            .format
+            .or(self.settings.format)
+            .unwrap_or(OutputFormat::Text);
+        let (logical, physical) = core_counts();
+
+        // Build the list of setups to try
+        let mut variants = vec![(true, true), (false, true)];
+        if physical < logical {
+            variants.push((true, false));
+        }
+        let mut trials = vec![];
+        for (pin, smt) in variants {
+            let max_cores = if smt { logical } else { physical } as u64;
+            for cores in sweep_core_counts(max_cores) {
+                let threads = hash_threads(cores, &sweep_thread_args(pin, smt, &args));
+                let pool = HashPool::new(&threads);
+                let report = Self::benchmark_pool(&pool, duration).await;
+                let core_ids: Vec<usize> = threads.iter().map(|thread| thread.core.id).collect();
+                trials.push(SweepTrial {
+                    cores,
+                    pin,
+                    smt,
+                    core_list: format_core_list(&core_ids),
+                    hashes_per_sec: report.hashes_per_sec,
+                });
+            }
+        }
+
+        // Recommend the fewest threads within tolerance of the best hashrate
+        let best = trials
+            .iter()
+            .map(|trial| trial.hashes_per_sec)
+            .fold(0.0, f64::max);
+        let recommended = trials
+            .iter()
+            .enumerate()
+            .filter(|(_, trial)| trial.hashes_per_sec >= best * (1.0 - SWEEP_TOLERANCE))
+            .min_by_key(|(_, trial)| trial.cores)
+            .map(|(i, _)| i);
+
+        // Hash on the recommended setup long enough for the cores to heat up, since short trials
+        // finish before throttling sets in
+        let mut thermal_falloff = None;
+        if let Some(trial) = recommended.map(|i| &trials[i]) {
+            if format.eq(&OutputFormat::Text) {
+                println!(
+                    "Measuring thermal falloff on {} cores over {} sec...",
+                    trial.cores,
+                    SUSTAINED_WINDOW * SUSTAINED_WINDOWS
+                );
+            }
+            let thread_args = sweep_thread_args(trial.pin, trial.smt, &args);
+            let threads = hash_threads(trial.cores, &thread_args);
+            let pool = HashPool::new(&threads);
+            let mut hashrates = vec![];
+            for _ in 0..SUSTAINED_WINDOWS {
+                let report = Self::benchmark_pool(&pool, SUSTAINED_WINDOW).await;
+                hashrates.push(report.hashes_per_sec);
+            }
+            let first = hashrates.first().copied().unwrap_or_default();
+            let last = hashrates.last().copied().unwrap_or_default();
+            thermal_falloff = Some(if first > 0.0 { 1.0 - last / first } else { 0.0 });
+        }
+        let report = SweepReport {
+            trials,
+            recommended,
+            thermal_falloff,
+        };
+
+        match format {
+            OutputFormat::Json => {
+                println!("{}", serde_json::to_string_pretty(&report).unwrap());
+            }
+            OutputFormat::Text => print_sweep(&report),
+        }
+
+        // Save the recommendation for mining, leaving the rest of the config file as written
+        if let Some(trial) = report.recommended.map(|i| &report.trials[i]) {
+            let values = [
+                ("cores", Some((trial.cores as i64).into())),
+                ("core_list", Some(trial.core_list.as_str().into())),
+                ("no_pin", Some((!trial.pin).into())),
+                ("no_smt", Some((!trial.smt).into())),
+                (
+                    "threads_per_core",
+                    args.threads.threads_per_core.map(|threads| (threads as i64).into()),
+                ),
+            ];
+            match ConfigFile::update(self.profile.as_deref(), &values) {
+                Ok(path) => {
+                    if format.eq(&OutputFormat::Text) {
+                        println!("Saved recommendation to {}", path.display());
+                    }
+                }
+                Err(err) => {
+                    eprintln!(
+                        "{} Failed to save recommendation: {}",
+                        "WARNING".bold().yellow(),
+                        err
+                    );
+                }
+            }
+        }
+    }
+
+    /// Hashes a fixed challenge on the pool for the given number of seconds, using the same
+    /// kernel as the mining loop.
+    pub async fn benchmark_pool(pool: &HashPool, duration: u64) -> BenchmarkReport {
+        let progress_bar = spinner::new_progress_bar();
         progress_bar.set_message(format!(
-            "Benchmarking. This will take {} sec...",
-            TEST_DURATION
+            "Benchmarking {} threads. This will take {} sec...",
+            pool.len(),
+            duration
         ));
-        let core_ids = core_affinity::get_core_ids().unwrap();
-        let handles: Vec<_> = core_ids
+
+        // Hash until the cutoff with no minimum difficulty
+        let challenge = [0; 32];
+        let timer = Instant::now();
+        let cutoff = timer + Duration::from_secs(duration);
+        let telemetry = pool.telemetry();
+        let results = pool.hash(challenge, cutoff, 0, None, Arc::clone(&telemetry)).await;
+        let elapsed = timer.elapsed().as_secs_f64().max(f64::EPSILON);
+        progress_bar.finish_and_clear();
+
+        // Aggregate stats
+        let cores: Vec<CoreReport> = results
+            .iter()
+            .map(|result| CoreReport {
+                core: result.core,
+                hashes_per_sec: result.hashes_checked as f64 / elapsed,
+                nonces_per_sec: result.nonces_checked as f64 / elapsed,
+                best_difficulty: result.difficulty,
+            })
+            .collect();
+        let hashes_per_sec: f64 = cores.iter().map(|core| core.hashes_per_sec).sum();
+        let nonces_per_sec: f64 = cores.iter().map(|core| core.nonces_per_sec).sum();
+        let round_hashes = hashes_per_sec * ROUND_DURATION;
+        let difficulties = telemetry
+            .difficulties()
             .into_iter()
-            .map(|i| {
-                std::thread::spawn({
-                    move || {
-                        let timer = Instant::now();
-                        let first_nonce = u64::MAX
-                            .saturating_div(args.cores)
-                            .saturating_mul(i.id as u64);
-                        let mut nonce = first_nonce;
-                        let mut memory = equix::SolverMemory::new();
-                        loop {
-                            // Return if core should not be used
-                            if (i.id as u64).ge(&args.cores) {
-                                return 0;
-                            }
-
-                            // Pin to core
-                            let _ = core_affinity::set_for_current(i);
-
-                            // Create hash
-                            let _hx = drillx::hash_with_memory(
-                                &mut memory,
-                                &challenge,
-                                &nonce.to_le_bytes(),
-                            );
-
-                            // Increment nonce
-                            nonce += 1;
-
-                            // Exit if time has elapsed
-                            if (timer.elapsed().as_secs() as i64).ge(&TEST_DURATION) {
-                                break;
-                            }
-                        }
-
-                        // Return hash count
-                        nonce - first_nonce
-                    }
-                })
+            .enumerate()
+            .map(|(difficulty, observed)| DifficultyReport {
+                difficulty: difficulty as u32,
+                observed,
+                round_probability: best_probability(difficulty as u32, round_hashes),
             })
+            .filter(|report| report.observed > 0 || report.round_probability >= 0.01)
             .collect();
 
-        // Join handles and return best nonce
-        let mut total_nonces = 0;
-        for h in handles {
-            if let Ok(count) = h.join() {
-                total_nonces += count;
-            }
+        BenchmarkReport {
+            threads: pool.len(),
+            duration: elapsed,
+            hashes_per_sec,
+            nonces_per_sec,
+            expected_best_difficulty: expected_best_difficulty(round_hashes),
+            cores,
+            difficulties,
         }
+    }
+}
 
-        // Update log
-        progress_bar.finish_with_message(format!(
-            "Hashpower: {} H/sec",
-            total_nonces.saturating_div(TEST_DURATION as u64),
-        ));
+fn print_report(report: &BenchmarkReport) {
+    println!(
+        "Threads: {}\nHashpower: {:.0} H/sec ({:.0} nonces/sec)\nExpected best difficulty in {}s: {:.2}",
+        report.threads,
+        report.hashes_per_sec,
+        report.nonces_per_sec,
+        ROUND_DURATION,
+        report.expected_best_difficulty
+    );
+    println!(
+        "\n  {:>6} {:>12} {:>12} {:>6}",
+        "Core", "H/sec", "Nonces/sec", "Best"
+    );
+    for core in report.cores.iter() {
+        println!(
+            "  {:>6} {:>12.0} {:>12.0} {:>6}",
+            core.core, core.hashes_per_sec, core.nonces_per_sec, core.best_difficulty
+        );
+    }
+    println!(
+        "\n  {:>10} {:>12} {:>12}",
+        "Difficulty", "Observed", "P(round)"
+    );
+    for difficulty in report.difficulties.iter() {
+        println!(
+            "  {:>10} {:>12} {:>11.2}%",
+            difficulty.difficulty,
+            difficulty.observed,
+            difficulty.round_probability * 100.0
+        );
+    }
+    println!();
+}
+
+/// The thread options of a sweep setup.
+fn sweep_thread_args(pin: bool, smt: bool, args: &BenchmarkArgs) -> ThreadArgs {
+    ThreadArgs {
+        core_list: None,
+        no_pin: !pin,
+        no_smt: !smt,
+        threads_per_core: args.threads.threads_per_core,
+    }
+}
+
+/// Returns powers of two up to the max core count, plus the max itself.
+fn sweep_core_counts(max_cores: u64) -> Vec<u64> {
+    let mut counts: Vec<u64> = std::iter::successors(Some(1u64), |n| n.checked_mul(2))
+        .take_while(|n| n.lt(&max_cores))
+        .collect();
+    if max_cores > 0 {
+        counts.push(max_cores);
+    }
+    counts
+}
+
+fn print_sweep(report: &SweepReport) {
+    println!(
+        "\n  {:>6} {:>5} {:>5} {:>12}  {:<9}",
+        "Cores", "Pin", "SMT", "H/sec", "Core list"
+    );
+    for (i, trial) in report.trials.iter().enumerate() {
+        let line = format!(
+            "  {:>6} {:>5} {:>5} {:>12.0}  {}",
+            trial.cores,
+            trial.pin,
+            trial.smt,
+            trial.hashes_per_sec,
+            trial.core_list
+        );
+        if report.recommended.eq(&Some(i)) {
+            println!("{}", line.bold().green());
+        } else {
+            println!("{}", line);
+        }
+    }
+    if let Some(trial) = report.recommended.map(|i| &report.trials[i]) {
+        println!(
+            "\n{} --cores {} --core-list {}{}{}",
+            "Recommended:".bold().green(),
+            trial.cores,
+            trial.core_list,
+            if trial.pin { "" } else { " --no-pin" },
+            if trial.smt { "" } else { " --no-smt" }
+        );
+    }
+    if let Some(falloff) = report.thermal_falloff {
+        println!(
+            "Thermal falloff: {:.1}% over {} sec",
+            falloff * 100.0,
+            SUSTAINED_WINDOW * SUSTAINED_WINDOWS
+        );
     }
 }
@@ -0,0 +1,160 @@
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
+
+use crate::args::BusStrategy;
+
+/// A bus and the activity used to choose between buses.
+#[derive(Clone, Copy, Debug)]
+pub struct BusCandidate {
+    /// The index of the bus in `BUS_ADDRESSES`.
+    pub index: usize,
+    pub rewards: u64,
+    /// The slot of the most recent transaction that wrote to the bus, or 0 if unknown.
+    pub last_write_slot: u64,
+}
+
+impl BusStrategy {
+    /// Returns true if the strategy needs the recent write activity of each bus.
+    pub fn needs_activity(&self) -> bool {
+        matches!(self, BusStrategy::LeastRecentlyWritten)
+    }
+
+    /// Picks a bus that can pay `reward` in full. If no bus can, picks the bus with the most
+    /// rewards. Returns the index of the bus, or none if there are no candidates.
+    pub fn select<R: Rng>(
+        &self,
+        candidates: &[BusCandidate],
+        reward: u64,
+        rng: &mut R,
+    ) -> Option<usize> {
+        let fits: Vec<BusCandidate> = candidates
+            .iter()
+            .filter(|bus| bus.rewards.ge(&reward))
+            .copied()
+            .collect();
+        if fits.is_empty() {
+            return most_rewards(candidates);
+        }
+        match self {
+            BusStrategy::MostRewards => most_rewards(&fits),
+            BusStrategy::WeightedRandom => {
+                let weights = fits.iter().map(|bus| bus.rewards);
+                match WeightedIndex::new(weights) {
+                    Ok(distribution) => Some(fits[distribution.sample(rng)].index),
+                    // Every bus is empty
+                    Err(_) => Some(fits[rng.gen_range(0..fits.len())].index),
+                }
+            }
+            BusStrategy::LeastRecentlyWritten => {
+                let oldest = fits.iter().map(|bus| bus.last_write_slot).min()?;
+                let idle: Vec<&BusCandidate> = fits
+                    .iter()
+                    .filter(|bus| bus.last_write_slot.eq(&oldest))
+                    .collect();
+                Some(idle[rng.gen_range(0..idle.len())].index)
+            }
+        }
+    }
+}
+
+fn most_rewards(candidates: &[BusCandidate]) -> Option<usize> {
+    candidates
+        .iter()
+        .max_by_key(|bus| bus.rewards)
+        .map(|bus| bus.index)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn bus(index: usize, rewards: u64, last_write_slot: u64) -> BusCandidate {
+        BusCandidate {
+            index,
+            rewards,
+            last_write_slot,
+        }
+    }
+
+    fn rng() -> StdRng {
+        StdRng::seed_from_u64(42)
+    }
+
+    #[test]
+    fn most_rewards_picks_the_fullest_bus() {
+        let buses = [bus(0, 100, 0), bus(1, 300, 0), bus(2, 200, 0)];
+        let selected = BusStrategy::MostRewards.select(&buses, 50, &mut rng());
+        assert_eq!(selected, Some(1));
+    }
+
+    #[test]
+    fn weighted_random_only_picks_buses_that_fit() {
+        let buses = [bus(0, 10, 0), bus(1, 500, 0), bus(2, 20, 0), bus(3, 400, 0)];
+        let mut rng = rng();
+        for _ in 0..100 {
+            let selected = BusStrategy::WeightedRandom.select(&buses, 100, &mut rng);
+            assert!(matches!(selected, Some(1) | Some(3)));
+        }
+    }
+
+    #[test]
+    fn weighted_random_favors_fuller_buses() {
+        let buses = [bus(0, 100, 0), bus(1, 900, 0)];
+        let mut rng = rng();
+        let fuller = (0..1000)
+            .filter(|_| BusStrategy::WeightedRandom.select(&buses, 1, &mut rng) == Some(1))
+            .count();
+        assert!(
+            (850..=950).contains(&fuller),
+            "picked the fuller bus {} times",
+            fuller
+        );
+    }
+
+    #[test]
+    fn weighted_random_is_deterministic_for_a_seed() {
+        let buses: Vec<BusCandidate> = (0..8).map(|index| bus(index, 100, 0)).collect();
+        let picks = |mut rng: StdRng| -> Vec<Option<usize>> {
+            (0..20)
+                .map(|_| BusStrategy::WeightedRandom.select(&buses, 1, &mut rng))
+                .collect()
+        };
+        assert_eq!(picks(rng()), picks(rng()));
+    }
+
+    #[test]
+    fn least_recently_written_picks_the_idlest_bus_that_fits() {
+        let buses = [
+            bus(0, 500, 10),
+            bus(1, 50, 2),
+            bus(2, 500, 5),
+            bus(3, 500, 8),
+        ];
+        let selected = BusStrategy::LeastRecentlyWritten.select(&buses, 100, &mut rng());
+        assert_eq!(selected, Some(2));
+    }
+
+    #[test]
+    fn falls_back_to_most_rewards_when_no_bus_fits() {
+        let buses = [bus(0, 10, 0), bus(1, 30, 0), bus(2, 20, 0)];
+        for strategy in [
+            BusStrategy::MostRewards,
+            BusStrategy::WeightedRandom,
+            BusStrategy::LeastRecentlyWritten,
+        ] {
+            assert_eq!(strategy.select(&buses, 100, &mut rng()), Some(1));
+        }
+    }
+
+    #[test]
+    fn no_candidates_selects_nothing() {
+        for strategy in [
+            BusStrategy::MostRewards,
+            BusStrategy::WeightedRandom,
+            BusStrategy::LeastRecentlyWritten,
+        ] {
+            assert_eq!(strategy.select(&[], 100, &mut rng()), None);
+        }
+    }
+}
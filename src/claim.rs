@@ -0,0 +1,61 @@
+use std::str::FromStr;
+
+use ore_api::consts::MINT_ADDRESS;
+use solana_program::pubkey::Pubkey;
+use spl_associated_token_account::get_associated_token_address;
+
+use crate::{
+    args::ClaimArgs,
+    exit_with_error,
+    utils::{
+        amount_f64_to_u64, amount_u64_to_string, create_token_account_ix, get_proof_with_authority,
+    },
+    Miner,
+};
+
+impl Miner {
+    pub async fn claim(&self, args: ClaimArgs) {
+        let signer = self.signer();
+        let pubkey = signer.pubkey();
+        let beneficiary = match args.to {
+            Some(to) => Pubkey::from_str(&to)
+                .unwrap_or_else(|_| exit_with_error(&format!("Invalid wallet address: {}", to))),
+            None => pubkey,
+        };
+
+        // Claiming the max needs the proof balance, which is only known online
+        let amount = match args.amount {
+            Some(amount) => amount_f64_to_u64(amount),
+            None if args.offline.sign_only => {
+                exit_with_error("Pass the amount to claim when signing offline")
+            }
+            None => {
+                get_proof_with_authority(&self.rpc_client, pubkey)
+                    .await
+                    .balance
+            }
+        };
+        if amount.eq(&0) {
+            println!("No rewards to claim");
+            return;
+        }
+
+        // Create the beneficiary token account if needed
+        let client = Some(self.rpc_client.as_ref()).filter(|_| !args.offline.sign_only);
+        let mut ixs = vec![];
+        ixs.extend(create_token_account_ix(client, self.fee_payer().pubkey(), beneficiary).await);
+        ixs.push(ore_api::instruction::claim(
+            pubkey,
+            get_associated_token_address(&beneficiary, &MINT_ADDRESS),
+            amount,
+        ));
+        if !args.offline.sign_only {
+            println!(
+                "Claiming {} ORE to {}",
+                amount_u64_to_string(amount),
+                beneficiary
+            );
+        }
+        self.send_or_sign(ixs, &args.offline).await;
+    }
+}
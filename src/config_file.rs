@@ -0,0 +1,462 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use colored::*;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{
+    args::{BusStrategy, ConfigFileArgs, ConfigFileCommand, JitoRegion, OutputFormat},
+    jito::check_tip_percentile,
+    signer::describe_signer_source,
+    Miner,
+};
+
+/// A template written by `ore config-file init`.
+const TEMPLATE: &str = r#"# ore-cli config file
+#
+# Settings are resolved in order of precedence:
+#   command line flag > ORE_* environment variable > profile > solana cli config
+#
+# Top-level settings apply to every profile. Select a profile with --profile <NAME>.
+
+# rpc = "https://api.mainnet-beta.solana.com,https://rpc.example.com"
+# keypair = "~/.config/solana/id.json"
+# fee_payer = "~/.config/solana/fee-payer.json"
+# signer_url = "http://10.0.0.2:8787"
+# nonce = "<NONCE_ACCOUNT_ADDRESS>"
+# nonce_authority = "~/.config/solana/nonce-authority.json"
+# cores = 4
+# core_list = "0,2,4-7"
+# no_pin = false
+# no_smt = false
+# threads_per_core = 1
+# buffer_time = 5
+# bus_strategy = "weighted-random"
+# auto_claim_threshold = 10.0
+# auto_claim_to = "<WALLET_ADDRESS>"
+# auto_claim_interval = 86400
+# auto_stake_threshold = 5.0
+# tui = false
+# control_socket = "/run/ore.sock"
+# jito = false
+# jito_region = "auto"
+# jito_url = "https://ny.mainnet.block-engine.jito.wtf/api/v1/transactions"
+# jito_tip_stream = "wss://bundles.jito.wtf/api/v1/bundles/tip_stream"
+# tip_percentile = 25
+# max_tip = 100000
+# format = "text"
+
+# [profile.rig-a]
+# rpc = "https://rpc.example.com"
+# cores = 16
+# core_list = "0-15"
+# jito = true
+"#;
+
+/// Local ore-cli settings. Every field is optional so profiles only need to list what they
+/// override.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Settings {
+    pub rpc: Option<String>,
+    pub keypair: Option<String>,
+    pub fee_payer: Option<String>,
+    /// The URL of an `ore signer-server` to sign with instead of the keypair.
+    pub signer_url: Option<String>,
+    /// A durable nonce account to sign transactions with instead of a recent blockhash.
+    pub nonce: Option<String>,
+    pub nonce_authority: Option<String>,
+    pub cores: Option<u64>,
+    pub core_list: Option<String>,
+    pub no_pin: Option<bool>,
+    pub no_smt: Option<bool>,
+    pub threads_per_core: Option<u64>,
+    pub buffer_time: Option<u64>,
+    pub bus_strategy: Option<BusStrategy>,
+    /// Claim rewards once the stake of the signer's own proof reaches this much ORE.
+    pub auto_claim_threshold: Option<f64>,
+    /// The wallet that receives automatic claims.
+    pub auto_claim_to: Option<String>,
+    /// The minimum number of seconds between automatic claims.
+    pub auto_claim_interval: Option<u64>,
+    /// The amount of ORE to keep staked when claiming automatically.
+    pub auto_stake_threshold: Option<f64>,
+    /// Whether `ore mine` shows the full-screen dashboard.
+    pub tui: Option<bool>,
+    /// The unix socket that `ore mine` serves control requests on and `ore ctl` connects to.
+    pub control_socket: Option<String>,
+    pub jito: Option<bool>,
+    /// The Jito block engine transactions endpoint. Overrides `jito_region`.
+    pub jito_url: Option<String>,
+    pub jito_region: Option<JitoRegion>,
+    /// The Jito tip stream websocket endpoint.
+    pub jito_tip_stream: Option<String>,
+    /// The landed tip percentile to pay when tipping Jito, one of 25, 50, 75, 95 or 99.
+    #[serde(default, deserialize_with = "deserialize_tip_percentile")]
+    pub tip_percentile: Option<u8>,
+    /// The maximum Jito tip to pay, in lamports.
+    pub max_tip: Option<u64>,
+    pub format: Option<OutputFormat>,
+}
+
+impl Settings {
+    /// Returns these settings with unset fields filled in from `base`.
+    pub fn or(self, base: &Settings) -> Self {
+        let base = base.clone();
+        Self {
+            rpc: self.rpc.or(base.rpc),
+            keypair: self.keypair.or(base.keypair),
+            fee_payer: self.fee_payer.or(base.fee_payer),
+            signer_url: self.signer_url.or(base.signer_url),
+            nonce: self.nonce.or(base.nonce),
+            nonce_authority: self.nonce_authority.or(base.nonce_authority),
+            cores: self.cores.or(base.cores),
+            core_list: self.core_list.or(base.core_list),
+            no_pin: self.no_pin.or(base.no_pin),
+            no_smt: self.no_smt.or(base.no_smt),
+            threads_per_core: self.threads_per_core.or(base.threads_per_core),
+            buffer_time: self.buffer_time.or(base.buffer_time),
+            bus_strategy: self.bus_strategy.or(base.bus_strategy),
+            auto_claim_threshold: self.auto_claim_threshold.or(base.auto_claim_threshold),
+            auto_claim_to: self.auto_claim_to.or(base.auto_claim_to),
+            auto_claim_interval: self.auto_claim_interval.or(base.auto_claim_interval),
+            auto_stake_threshold: self.auto_stake_threshold.or(base.auto_stake_threshold),
+            tui: self.tui.or(base.tui),
+            control_socket: self.control_socket.or(base.control_socket),
+            jito: self.jito.or(base.jito),
+            jito_url: self.jito_url.or(base.jito_url),
+            jito_region: self.jito_region.or(base.jito_region),
+            jito_tip_stream: self.jito_tip_stream.or(base.jito_tip_stream),
+            tip_percentile: self.tip_percentile.or(base.tip_percentile),
+            max_tip: self.max_tip.or(base.max_tip),
+            format: self.format.or(base.format),
+        }
+    }
+}
+
+/// Rejects tip percentiles the Jito tip stream does not publish when loading the config file.
+fn deserialize_tip_percentile<'de, D>(deserializer: D) -> Result<Option<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<u8>::deserialize(deserializer)?
+        .map(check_tip_percentile)
+        .transpose()
+        .map_err(serde::de::Error::custom)
+}
+
+/// Expands a leading `~` in a path to the home directory.
+pub fn expand_home(path: String) -> String {
+    match (path.strip_prefix("~/"), dirs_next::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest).to_string_lossy().into_owned(),
+        _ => path,
+    }
+}
+
+/// The ore-cli config file, stored as TOML at `~/.config/ore/ore.toml`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ConfigFile {
+    #[serde(flatten)]
+    pub settings: Settings,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profile: BTreeMap<String, Settings>,
+}
+
+impl ConfigFile {
+    /// The default location of the config file, `~/.config/ore/ore.toml`.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs_next::home_dir().map(|home| home.join(".config").join("ore").join("ore.toml"))
+    }
+
+    /// Loads the config file from the default path, falling back to empty settings if it does
+    /// not exist. Exits if the file exists but cannot be parsed.
+    pub fn load() -> Self {
+        let Some(data) = Self::default_path().and_then(|path| std::fs::read_to_string(path).ok())
+        else {
+            return Self::default();
+        };
+        toml::from_str(&data).unwrap_or_else(|err| {
+            eprintln!("{} Invalid ore config file: {}", "ERROR".bold().red(), err);
+            std::process::exit(1);
+        })
+    }
+
+    /// Sets keys of a profile, or the top-level settings, in the config file at the default
+    /// path. Only those keys change, so comments and the rest of the file are kept as written.
+    /// Keys with no value are removed.
+    pub fn update(
+        profile: Option<&str>,
+        values: &[(&str, Option<toml_edit::Value>)],
+    ) -> std::io::Result<PathBuf> {
+        let path = Self::default_path().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "No home directory found")
+        })?;
+        // Only a missing file starts empty, so a file that cannot be read is never overwritten
+        let data = match std::fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(err) if err.kind().eq(&std::io::ErrorKind::NotFound) => String::new(),
+            Err(err) => return Err(err),
+        };
+        Self::write(&update_document(&data, profile, values)?)
+    }
+
+    fn write(data: &str) -> std::io::Result<PathBuf> {
+        let path = Self::default_path().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "No home directory found")
+        })?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, data)?;
+        Ok(path)
+    }
+
+    /// Resolves the settings of a profile on top of the top-level settings.
+    /// Exits if the profile does not exist.
+    pub fn settings(&self, profile: Option<&str>) -> Settings {
+        match profile {
+            Some(name) => match self.profile.get(name) {
+                Some(settings) => settings.clone().or(&self.settings),
+                None => {
+                    eprintln!(
+                        "{} Profile `{}` not found in ore config file",
+                        "ERROR".bold().red(),
+                        name
+                    );
+                    std::process::exit(1);
+                }
+            },
+            None => self.settings.clone(),
+        }
+    }
+}
+
+/// Sets keys of a profile, or the top-level settings, in the given config file contents and
+/// returns the updated contents.
+fn update_document(
+    data: &str,
+    profile: Option<&str>,
+    values: &[(&str, Option<toml_edit::Value>)],
+) -> std::io::Result<String> {
+    let mut document: toml_edit::Document = data
+        .parse()
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+    let table = match profile {
+        Some(name) => {
+            let profiles = document
+                .entry("profile")
+                .or_insert_with(toml_edit::table)
+                .as_table_mut()
+                .ok_or_else(|| not_a_table("profile"))?;
+            profiles.set_implicit(true);
+            profiles
+                .entry(name)
+                .or_insert_with(toml_edit::table)
+                .as_table_mut()
+                .ok_or_else(|| not_a_table(&format!("profile.{}", name)))?
+        }
+        None => document.as_table_mut(),
+    };
+    for (key, value) in values {
+        match value {
+            Some(value) => {
+                // Keep the comments before the key and after the old value
+                let mut value = value.clone();
+                match table.get_mut(key) {
+                    Some(item) => {
+                        if let Some(old) = item.as_value() {
+                            *value.decor_mut() = old.decor().clone();
+                        }
+                        *item = toml_edit::Item::Value(value);
+                    }
+                    None => {
+                        table.insert(key, toml_edit::Item::Value(value));
+                    }
+                }
+            }
+            None => {
+                table.remove(key);
+            }
+        }
+    }
+    Ok(document.to_string())
+}
+
+fn not_a_table(key: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("`{}` in the ore config file is not a table", key),
+    )
+}
+
+impl Miner {
+    pub async fn config_file(&self, args: ConfigFileArgs) {
+        match args.command {
+            ConfigFileCommand::Init(args) => {
+                let exists = ConfigFile::default_path().map_or(false, |path| path.exists());
+                if exists && !args.force {
+                    eprintln!(
+                        "{} Ore config file already exists. Pass --force to overwrite it.",
+                        "ERROR".bold().red()
+                    );
+                    std::process::exit(1);
+                }
+                match ConfigFile::write(TEMPLATE) {
+                    Ok(path) => println!("Wrote ore config file to {}", path.display()),
+                    Err(err) => {
+                        eprintln!(
+                            "{} Failed to write config file: {}",
+                            "ERROR".bold().red(),
+                            err
+                        );
+                        std::process::exit(1);
+                    }
+                }
+            }
+            ConfigFileCommand::Show(_) => {
+                if let Some(path) = ConfigFile::default_path() {
+                    println!("Config file: {}", path.display());
+                }
+                println!(
+                    "Profile: {}\n",
+                    self.profile.as_deref().unwrap_or("(default)")
+                );
+                // Show inline keypairs by their public key only
+                let mut settings = self.settings.clone();
+                for source in [
+                    &mut settings.keypair,
+                    &mut settings.fee_payer,
+                    &mut settings.nonce_authority,
+                ]
+                .into_iter()
+                .flatten()
+                {
+                    *source = describe_signer_source(source);
+                }
+                println!("{}", toml::to_string_pretty(&settings).unwrap());
+                for endpoint in self.rpc_pool.ranked() {
+                    println!("Effective RPC: {}", endpoint.url());
+                }
+                if let Some(keypair) = &self.keypair_filepath {
+                    println!("Effective keypair: {}", describe_signer_source(keypair));
+                }
+                if let Some(fee_payer) = &self.fee_payer_filepath {
+                    println!("Effective fee payer: {}", describe_signer_source(fee_payer));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"# My rigs
+
+# The shared node
+rpc = "https://top.example.com"
+keypair = "~/top.json"
+cores = 4 # leave some for the OS
+
+# tip_percentile = 25
+
+[profile.rig-a]
+rpc = "https://rig-a.example.com"   # private node
+cores = 16
+"#;
+
+    fn update(profile: Option<&str>, values: &[(&str, Option<toml_edit::Value>)]) -> String {
+        update_document(CONFIG, profile, values).unwrap()
+    }
+
+    #[test]
+    fn or_prefers_set_values() {
+        let profile = Settings {
+            rpc: Some("https://profile.example.com".to_string()),
+            cores: None,
+            jito: Some(false),
+            ..Default::default()
+        };
+        let base = Settings {
+            rpc: Some("https://base.example.com".to_string()),
+            keypair: Some("~/base.json".to_string()),
+            cores: Some(8),
+            jito: Some(true),
+            ..Default::default()
+        };
+        let settings = profile.or(&base);
+        assert_eq!(settings.rpc.as_deref(), Some("https://profile.example.com"));
+        assert_eq!(settings.keypair.as_deref(), Some("~/base.json"));
+        assert_eq!(settings.cores, Some(8));
+        assert_eq!(settings.jito, Some(false));
+        assert_eq!(settings.max_tip, None);
+    }
+
+    #[test]
+    fn profiles_override_top_level_settings() {
+        let config: ConfigFile = toml::from_str(CONFIG).unwrap();
+        let top = config.settings(None);
+        assert_eq!(top.rpc.as_deref(), Some("https://top.example.com"));
+        assert_eq!(top.cores, Some(4));
+        let rig = config.settings(Some("rig-a"));
+        assert_eq!(rig.rpc.as_deref(), Some("https://rig-a.example.com"));
+        assert_eq!(rig.cores, Some(16));
+        assert_eq!(rig.keypair.as_deref(), Some("~/top.json"));
+    }
+
+    #[test]
+    fn update_keeps_comments_and_formatting() {
+        let updated = update(None, &[("cores", Some(8.into()))]);
+        assert_eq!(
+            updated,
+            CONFIG.replace("cores = 4 # leave", "cores = 8 # leave")
+        );
+        let updated = update(None, &[("rpc", Some("https://top.example.net".into()))]);
+        assert_eq!(
+            updated,
+            CONFIG.replace("\"https://top.example.com\"", "\"https://top.example.net\"")
+        );
+        let updated = update(
+            Some("rig-a"),
+            &[("rpc", Some("https://rig-a.example.net".into()))],
+        );
+        assert_eq!(
+            updated,
+            CONFIG.replace(
+                "\"https://rig-a.example.com\"",
+                "\"https://rig-a.example.net\""
+            )
+        );
+    }
+
+    #[test]
+    fn update_adds_and_removes_keys() {
+        let updated = update(
+            Some("rig-b"),
+            &[("cores", Some(2.into())), ("no_smt", Some(true.into()))],
+        );
+        assert!(updated.starts_with(CONFIG));
+        assert!(!updated.contains("[profile]\n"));
+        let config: ConfigFile = toml::from_str(&updated).unwrap();
+        let rig = config.settings(Some("rig-b"));
+        assert_eq!(rig.cores, Some(2));
+        assert_eq!(rig.no_smt, Some(true));
+
+        let updated = update(None, &[("keypair", None)]);
+        assert_eq!(updated, CONFIG.replace("keypair = \"~/top.json\"\n", ""));
+    }
+
+    #[test]
+    fn update_starts_missing_files_empty() {
+        let updated = update_document("", None, &[("cores", Some(8.into()))]).unwrap();
+        assert_eq!(updated, "cores = 8\n");
+    }
+
+    #[test]
+    fn update_rejects_invalid_files() {
+        let err = update_document("cores = ", None, &[]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        let err = update_document("profile = 1\n", Some("rig-a"), &[]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,140 @@
+use std::sync::{
+    atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+    Arc,
+};
+
+use crate::dashboard::restore_terminal;
+
+/// Settings of a running miner that can change without restarting it. The mining loop reads
+/// them between rounds, while the dashboard, signal handlers and control socket change them.
+pub struct MineControl {
+    paused: AtomicBool,
+    shutting_down: AtomicBool,
+    /// Set while paused or shutting down, so the hashing threads stop early.
+    halted: Arc<AtomicBool>,
+    cores: AtomicU64,
+    buffer_time: AtomicU64,
+    /// The difficulty to keep hashing for past the cutoff, or 0 for none.
+    target_difficulty: AtomicU32,
+}
+
+impl MineControl {
+    pub fn new(cores: u64, buffer_time: u64) -> Self {
+        Self {
+            paused: AtomicBool::new(false),
+            shutting_down: AtomicBool::new(false),
+            halted: Arc::new(AtomicBool::new(false)),
+            cores: AtomicU64::new(cores),
+            buffer_time: AtomicU64::new(buffer_time),
+            target_difficulty: AtomicU32::new(0),
+        }
+    }
+
+    /// Returns true while hashing is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Pauses or resumes hashing. Pausing stops the current round at once.
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+        self.halted
+            .store(paused || self.is_shutting_down(), Ordering::Relaxed);
+    }
+
+    /// Returns true once the miner was asked to exit.
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::Relaxed)
+    }
+
+    /// Asks the miner to exit once the current submission is done, stopping hashing at once.
+    /// Returns false if it was already asked to.
+    pub fn shut_down(&self) -> bool {
+        self.halted.store(true, Ordering::Relaxed);
+        !self.shutting_down.swap(true, Ordering::Relaxed)
+    }
+
+    /// Returns the flag the hashing threads check to stop early.
+    pub fn halt_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.halted)
+    }
+
+    /// The number of cores to hash with from the next round on.
+    pub fn cores(&self) -> u64 {
+        self.cores.load(Ordering::Relaxed)
+    }
+
+    /// Changes the number of cores to hash with, keeping it between 1 and the cores available.
+    pub fn set_cores(&self, cores: u64) -> u64 {
+        let cores = cores.clamp(1, num_cpus::get() as u64);
+        self.cores.store(cores, Ordering::Relaxed);
+        cores
+    }
+
+    /// The number of seconds before the deadline to stop hashing, from the next round on.
+    pub fn buffer_time(&self) -> u64 {
+        self.buffer_time.load(Ordering::Relaxed)
+    }
+
+    pub fn set_buffer_time(&self, buffer_time: u64) {
+        self.buffer_time.store(buffer_time, Ordering::Relaxed);
+    }
+
+    /// The difficulty to keep hashing for past the cutoff, if any.
+    pub fn target_difficulty(&self) -> Option<u32> {
+        match self.target_difficulty.load(Ordering::Relaxed) {
+            0 => None,
+            difficulty => Some(difficulty),
+        }
+    }
+
+    pub fn set_target_difficulty(&self, difficulty: Option<u32>) {
+        self.target_difficulty
+            .store(difficulty.unwrap_or(0), Ordering::Relaxed);
+    }
+}
+
+/// Handles signals while mining. SIGINT and SIGTERM stop hashing and exit once the current
+/// submission is done, and a second one exits at once. SIGUSR1 pauses hashing and SIGUSR2
+/// resumes it.
+#[cfg(unix)]
+pub async fn handle_signals(control: Arc<MineControl>, tui: bool) {
+    use tokio::signal::unix::{signal, SignalKind};
+    let (Ok(mut interrupt), Ok(mut terminate), Ok(mut pause), Ok(mut resume)) = (
+        signal(SignalKind::interrupt()),
+        signal(SignalKind::terminate()),
+        signal(SignalKind::user_defined1()),
+        signal(SignalKind::user_defined2()),
+    ) else {
+        return;
+    };
+    loop {
+        tokio::select! {
+            _ = interrupt.recv() => request_shutdown(&control, tui),
+            _ = terminate.recv() => request_shutdown(&control, tui),
+            _ = pause.recv() => control.set_paused(true),
+            _ = resume.recv() => control.set_paused(false),
+        }
+    }
+}
+
+/// Handles Ctrl+C while mining, like SIGINT on unix.
+#[cfg(not(unix))]
+pub async fn handle_signals(control: Arc<MineControl>, tui: bool) {
+    while tokio::signal::ctrl_c().await.is_ok() {
+        request_shutdown(&control, tui);
+    }
+}
+
+/// Asks the miner to shut down, or exits at once if it was already asked to.
+pub fn request_shutdown(control: &MineControl, tui: bool) {
+    if !control.shut_down() {
+        if tui {
+            restore_terminal();
+        }
+        std::process::exit(130);
+    }
+    if !tui {
+        println!("Shutting down after the current submission. Press Ctrl+C again to exit now.");
+    }
+}
@@ -0,0 +1,575 @@
+use std::{
+    cell::{Cell, RefCell},
+    io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use colored::*;
+use futures::{stream::FuturesUnordered, StreamExt};
+use ore_api::state::Proof;
+use ore_utils::AccountDeserialize;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use solana_program::pubkey::Pubkey;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+
+use crate::{
+    args::{CtlArgs, CtlCommand},
+    config_file::expand_home,
+    control::MineControl,
+    dashboard::{report, Dashboard},
+    exit_with_error,
+    session::{Session, SessionStats},
+    utils::{amount_f64_to_u64, amount_u64_to_f64, amount_u64_to_string, proof_pubkey},
+    Miner,
+};
+
+/// The largest request the control socket reads, in bytes.
+const MAX_REQUEST_SIZE: u64 = 16 * 1024;
+
+/// How long a client may take to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often to check whether mining ended while serving requests.
+const DONE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long `ore ctl` waits for a response. Claims are only answered once confirmed.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The JSON-RPC error codes used by the control socket.
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const REQUEST_FAILED: i64 = -32000;
+
+/// A JSON-RPC 2.0 request, sent as a single line.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RpcRequest {
+    pub jsonrpc: String,
+    #[serde(default)]
+    pub id: Value,
+    pub method: String,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub params: Value,
+}
+
+/// A JSON-RPC 2.0 response, sent as a single line.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RpcResponse {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+/// The params of `set_cores`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CoresParams {
+    pub cores: u64,
+}
+
+/// The params of `set_buffer_time`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BufferTimeParams {
+    pub seconds: u64,
+}
+
+/// The params of `set_target_difficulty`. No difficulty submits at the cutoff again.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TargetDifficultyParams {
+    pub difficulty: Option<u32>,
+}
+
+/// The params of `set_tip`. Unset fields are left as they are.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TipParams {
+    pub percentile: Option<u8>,
+    pub max_tip: Option<u64>,
+}
+
+/// The params of `claim`. By default all rewards are claimed to the signer.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ClaimParams {
+    pub amount: Option<f64>,
+    pub to: Option<String>,
+}
+
+/// The result of `status`, and of every method that changes a setting.
+#[derive(Debug, Serialize)]
+pub struct MinerStatus {
+    pub signer: String,
+    pub paused: bool,
+    pub shutting_down: bool,
+    pub cores: u64,
+    pub buffer_time: u64,
+    pub target_difficulty: Option<u32>,
+    /// The Jito tip paid with the next transaction, in lamports.
+    pub tip: u64,
+    /// The landed tip percentile paid, or none if Jito is disabled.
+    pub tip_percentile: Option<u8>,
+    pub max_tip: Option<u64>,
+    pub session: SessionStats,
+}
+
+/// The result of `claim`.
+#[derive(Debug, Serialize)]
+pub struct ClaimResult {
+    pub signature: String,
+    /// The amount claimed, in ORE.
+    pub amount: f64,
+    pub beneficiary: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl RpcResponse {
+    fn new(id: Value, result: Result<Value, RpcError>) -> Self {
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+/// The unix socket a miner serves control requests on. The socket file is removed when it is
+/// dropped.
+pub struct ControlSocket {
+    #[cfg(unix)]
+    listener: tokio::net::UnixListener,
+    path: PathBuf,
+}
+
+impl ControlSocket {
+    /// Listens on the given path, replacing a socket left behind by a miner that did not exit
+    /// cleanly. Only the current user may connect.
+    #[cfg(unix)]
+    pub fn bind(path: &str) -> Result<Self, String> {
+        use std::os::unix::{
+            fs::{DirBuilderExt, FileTypeExt},
+            net::UnixStream,
+        };
+        let path = PathBuf::from(path);
+        if let Ok(metadata) = std::fs::symlink_metadata(&path) {
+            if !metadata.file_type().is_socket() {
+                return Err(format!("{} exists and is not a socket", path.display()));
+            }
+            if UnixStream::connect(&path).is_ok() {
+                return Err(format!(
+                    "Another miner is already listening on {}",
+                    path.display()
+                ));
+            }
+            std::fs::remove_file(&path).map_err(|err| {
+                format!("Failed to remove stale socket {}: {}", path.display(), err)
+            })?;
+        }
+
+        // Bind inside a directory only the current user can enter and restrict the socket there,
+        // so nobody else can connect before it is moved into place
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| format!("{} is not a file path", path.display()))?;
+        let dir = path.with_file_name(format!(
+            ".{}.{}",
+            file_name.to_string_lossy(),
+            std::process::id()
+        ));
+        std::fs::DirBuilder::new()
+            .mode(0o700)
+            .create(&dir)
+            .map_err(|err| format!("Failed to create {}: {}", dir.display(), err))?;
+        let result = Self::bind_in(&dir, &path);
+        let _ = std::fs::remove_dir_all(&dir);
+        result
+    }
+
+    /// Listens on a socket created in the given private directory, then moves it to the path.
+    #[cfg(unix)]
+    fn bind_in(dir: &Path, path: &Path) -> Result<Self, String> {
+        use std::os::unix::fs::PermissionsExt;
+        let private_path = dir.join("socket");
+        let listener = tokio::net::UnixListener::bind(&private_path)
+            .map_err(|err| format!("Failed to listen on {}: {}", path.display(), err))?;
+        std::fs::set_permissions(&private_path, std::fs::Permissions::from_mode(0o600))
+            .map_err(|err| format!("Failed to restrict {}: {}", path.display(), err))?;
+        std::fs::rename(&private_path, path)
+            .map_err(|err| format!("Failed to listen on {}: {}", path.display(), err))?;
+        Ok(Self {
+            listener,
+            path: path.to_path_buf(),
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn bind(_path: &str) -> Result<Self, String> {
+        Err("Control sockets are only supported on unix".to_string())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+impl Miner {
+    /// Serves control requests until the mining loop ends, with one request per connection.
+    /// Requests are handled concurrently alongside mining, so a claim waiting to confirm does
+    /// not hold up other requests. Once mining ends no new requests are accepted, but requests in
+    /// flight are answered before returning.
+    #[cfg(unix)]
+    pub async fn serve_control(
+        &self,
+        socket: &ControlSocket,
+        control: &MineControl,
+        session: &RefCell<Session>,
+        dashboard: Option<&Dashboard>,
+        mining_done: &Cell<bool>,
+    ) {
+        let mut requests = FuturesUnordered::new();
+        while !mining_done.get() || !requests.is_empty() {
+            tokio::select! {
+                accepted = socket.listener.accept(), if !mining_done.get() => {
+                    if let Ok((stream, _)) = accepted {
+                        requests.push(
+                            self.handle_control_request(stream, control, session, dashboard),
+                        );
+                    }
+                }
+                Some(result) = requests.next(), if !requests.is_empty() => {
+                    if let Err(err) = result {
+                        report(
+                            dashboard,
+                            format!(
+                                "{} Control request failed: {}",
+                                "WARNING".bold().yellow(),
+                                err
+                            ),
+                        );
+                    }
+                }
+                _ = tokio::time::sleep(DONE_POLL_INTERVAL) => {}
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub async fn serve_control(
+        &self,
+        _socket: &ControlSocket,
+        _control: &MineControl,
+        _session: &RefCell<Session>,
+        _dashboard: Option<&Dashboard>,
+        _mining_done: &Cell<bool>,
+    ) {
+    }
+
+    async fn handle_control_request<S>(
+        &self,
+        stream: S,
+        control: &MineControl,
+        session: &RefCell<Session>,
+        dashboard: Option<&Dashboard>,
+    ) -> io::Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let mut reader = BufReader::new(stream.take(MAX_REQUEST_SIZE));
+        let mut line = String::new();
+        tokio::time::timeout(REQUEST_TIMEOUT, reader.read_line(&mut line))
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "Request timed out"))??;
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(request) => match serde_json::from_value::<RpcRequest>(request) {
+                Ok(request) if request.jsonrpc.eq("2.0") => {
+                    let id = request.id.clone();
+                    let result = self.dispatch(request, control, session, dashboard).await;
+                    RpcResponse::new(id, result)
+                }
+                Ok(request) => RpcResponse::new(
+                    request.id,
+                    Err(RpcError::new(
+                        INVALID_REQUEST,
+                        "Only JSON-RPC 2.0 is supported",
+                    )),
+                ),
+                Err(err) => RpcResponse::new(
+                    Value::Null,
+                    Err(RpcError::new(INVALID_REQUEST, err.to_string())),
+                ),
+            },
+            Err(err) => RpcResponse::new(
+                Value::Null,
+                Err(RpcError::new(PARSE_ERROR, err.to_string())),
+            ),
+        };
+        let mut body = serde_json::to_string(&response)?;
+        body.push('\n');
+        let mut stream = reader.into_inner().into_inner();
+        stream.write_all(body.as_bytes()).await?;
+        stream.shutdown().await
+    }
+
+    async fn dispatch(
+        &self,
+        request: RpcRequest,
+        control: &MineControl,
+        session: &RefCell<Session>,
+        dashboard: Option<&Dashboard>,
+    ) -> Result<Value, RpcError> {
+        match request.method.as_str() {
+            "status" => {}
+            "pause" => control.set_paused(true),
+            "resume" => control.set_paused(false),
+            // The mining loop reports the new core count once it applies it
+            "set_cores" => {
+                let params: CoresParams = parse_params(request.params)?;
+                control.set_cores(params.cores);
+            }
+            "set_buffer_time" => {
+                let params: BufferTimeParams = parse_params(request.params)?;
+                control.set_buffer_time(params.seconds);
+                report(
+                    dashboard,
+                    format!("Buffer time set to {}s from the next round", params.seconds),
+                );
+            }
+            "set_target_difficulty" => {
+                let params: TargetDifficultyParams = parse_params(request.params)?;
+                control.set_target_difficulty(params.difficulty);
+                report(
+                    dashboard,
+                    match params.difficulty {
+                        Some(difficulty) => format!("Hashing until difficulty {}", difficulty),
+                        None => "Submitting at the cutoff".to_string(),
+                    },
+                );
+            }
+            "set_tip" => {
+                let params: TipParams = parse_params(request.params)?;
+                let Some(tip_settings) = &self.tip_settings else {
+                    return Err(RpcError::new(
+                        REQUEST_FAILED,
+                        "Jito is disabled. Restart the miner with --jito to tip.",
+                    ));
+                };
+                if let Some(percentile) = params.percentile {
+                    tip_settings
+                        .set_percentile(percentile)
+                        .map_err(|err| RpcError::new(INVALID_PARAMS, err))?;
+                }
+                if let Some(max_tip) = params.max_tip {
+                    tip_settings.set_max_tip(max_tip);
+                    let mut tip = self.tip.write().unwrap();
+                    *tip = (*tip).min(max_tip);
+                }
+                report(
+                    dashboard,
+                    format!(
+                        "Tipping the {}th percentile, up to {} lamports",
+                        tip_settings.percentile(),
+                        tip_settings.max_tip()
+                    ),
+                );
+            }
+            "claim" => {
+                let params: ClaimParams = parse_params(request.params)?;
+                let result = self.claim_from_control(params, dashboard).await?;
+                return Ok(serde_json::to_value(result).unwrap());
+            }
+            method => {
+                return Err(RpcError::new(
+                    METHOD_NOT_FOUND,
+                    format!("Unknown method: {}", method),
+                ))
+            }
+        }
+        let status = MinerStatus {
+            signer: self.signer().pubkey().to_string(),
+            paused: control.is_paused(),
+            shutting_down: control.is_shutting_down(),
+            cores: control.cores(),
+            buffer_time: control.buffer_time(),
+            target_difficulty: control.target_difficulty(),
+            tip: *self.tip.read().unwrap(),
+            tip_percentile: self.tip_settings.as_ref().map(|s| s.percentile()),
+            max_tip: self.tip_settings.as_ref().map(|s| s.max_tip()),
+            session: session.borrow().stats(),
+        };
+        Ok(serde_json::to_value(status).unwrap())
+    }
+
+    /// Claims the signer's rewards on request, answering once the claim is confirmed.
+    async fn claim_from_control(
+        &self,
+        params: ClaimParams,
+        dashboard: Option<&Dashboard>,
+    ) -> Result<ClaimResult, RpcError> {
+        let signer = self.signer().pubkey();
+        let beneficiary = match params.to {
+            Some(to) => Pubkey::from_str(&to).map_err(|_| {
+                RpcError::new(INVALID_PARAMS, format!("Invalid wallet address: {}", to))
+            })?,
+            None => signer,
+        };
+        let data = self
+            .rpc_client
+            .get_account_data(&proof_pubkey(signer))
+            .await
+            .map_err(|err| {
+                RpcError::new(REQUEST_FAILED, format!("Failed to fetch proof: {}", err))
+            })?;
+        let proof = Proof::try_from_bytes(&data)
+            .map_err(|_| RpcError::new(REQUEST_FAILED, "Invalid proof account"))?;
+        let amount = params.amount.map_or(proof.balance, amount_f64_to_u64);
+        if amount.eq(&0) {
+            return Err(RpcError::new(REQUEST_FAILED, "No rewards to claim"));
+        }
+        if amount.gt(&proof.balance) {
+            return Err(RpcError::new(
+                INVALID_PARAMS,
+                format!(
+                    "Cannot claim {} ORE, only {} ORE is staked",
+                    amount_u64_to_string(amount),
+                    amount_u64_to_string(proof.balance)
+                ),
+            ));
+        }
+        let signature = self
+            .send_claim(beneficiary, amount)
+            .await
+            .map_err(|err| RpcError::new(REQUEST_FAILED, format!("Claim failed: {}", err)))?;
+        report(
+            dashboard,
+            format!(
+                "{} {} ORE to {} ({})",
+                "Claimed".bold().green(),
+                amount_u64_to_string(amount),
+                beneficiary,
+                signature
+            ),
+        );
+        Ok(ClaimResult {
+            signature: signature.to_string(),
+            amount: amount_u64_to_f64(amount),
+            beneficiary: beneficiary.to_string(),
+        })
+    }
+
+    /// Sends a request to a running miner and prints the result as JSON.
+    pub async fn ctl(&self, args: CtlArgs) {
+        let path = args
+            .socket
+            .or(self.settings.control_socket.clone().map(expand_home))
+            .unwrap_or_else(|| {
+                exit_with_error("No control socket provided. Pass --socket or set control_socket.")
+            });
+        let (method, params) = match args.command {
+            CtlCommand::Status => ("status", json!(null)),
+            CtlCommand::Pause => ("pause", json!(null)),
+            CtlCommand::Resume => ("resume", json!(null)),
+            CtlCommand::Cores(args) => ("set_cores", json!(CoresParams { cores: args.cores })),
+            CtlCommand::BufferTime(args) => (
+                "set_buffer_time",
+                json!(BufferTimeParams {
+                    seconds: args.seconds
+                }),
+            ),
+            CtlCommand::Difficulty(args) => (
+                "set_target_difficulty",
+                json!(TargetDifficultyParams {
+                    difficulty: args.difficulty
+                }),
+            ),
+            CtlCommand::Tip(args) => (
+                "set_tip",
+                json!(TipParams {
+                    percentile: args.percentile,
+                    max_tip: args.max_tip
+                }),
+            ),
+            CtlCommand::Claim(args) => (
+                "claim",
+                json!(ClaimParams {
+                    amount: args.amount,
+                    to: args.to
+                }),
+            ),
+        };
+        let request = RpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: json!(1),
+            method: method.to_string(),
+            params,
+        };
+        let response = send_request(&path, &request).await.unwrap_or_else(|err| {
+            exit_with_error(&format!("Failed to reach the miner at {}: {}", path, err))
+        });
+        match (response.result, response.error) {
+            (_, Some(err)) => exit_with_error(&err.message),
+            (Some(result), None) => println!("{}", serde_json::to_string_pretty(&result).unwrap()),
+            (None, None) => exit_with_error("The miner sent an empty response"),
+        }
+    }
+}
+
+/// Parses the params of a request. Missing params count as an empty object.
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    let params = match params {
+        Value::Null => json!({}),
+        params => params,
+    };
+    serde_json::from_value(params).map_err(|err| RpcError::new(INVALID_PARAMS, err.to_string()))
+}
+
+#[cfg(unix)]
+async fn send_request(path: &str, request: &RpcRequest) -> io::Result<RpcResponse> {
+    let mut stream = tokio::net::UnixStream::connect(path).await?;
+    let mut body = serde_json::to_string(request)?;
+    body.push('\n');
+    stream.write_all(body.as_bytes()).await?;
+    let mut line = String::new();
+    tokio::time::timeout(
+        RESPONSE_TIMEOUT,
+        BufReader::new(stream).read_line(&mut line),
+    )
+    .await
+    .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "Timed out waiting for a response"))??;
+    serde_json::from_str(&line).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+#[cfg(not(unix))]
+async fn send_request(_path: &str, _request: &RpcRequest) -> io::Result<RpcResponse> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Control sockets are only supported on unix",
+    ))
+}
@@ -0,0 +1,274 @@
+use std::collections::BTreeSet;
+
+use colored::*;
+use core_affinity::CoreId;
+
+use crate::{args::ThreadArgs, config_file::Settings};
+
+/// A single hashing thread and the core it should be pinned to, if any.
+#[derive(Clone, Copy, Debug)]
+pub struct HashThread {
+    pub core: CoreId,
+    pub pin: bool,
+}
+
+impl HashThread {
+    /// Pins the calling thread to its core, unless pinning is disabled.
+    pub fn pin(&self) {
+        if self.pin {
+            let _ = core_affinity::set_for_current(self.core);
+        }
+    }
+}
+
+/// Resolves the hashing threads to spawn from the requested core count and thread options.
+pub fn hash_threads(cores: u64, args: &ThreadArgs) -> Vec<HashThread> {
+    let available = core_affinity::get_core_ids().unwrap_or_default();
+
+    // Pick cores from the explicit list, or the first available ids.
+    let mut core_ids: Vec<CoreId> = match &args.core_list {
+        Some(list) => {
+            let ids = parse_core_list(list).unwrap_or_else(|err| {
+                eprintln!("{} {}", "ERROR".bold().red(), err);
+                std::process::exit(1);
+            });
+            ids.into_iter()
+                .filter(|id| {
+                    let exists = available.iter().any(|core| core.id.eq(id));
+                    if !exists {
+                        println!(
+                            "{} Core {} is not available and will be skipped",
+                            "WARNING".bold().yellow(),
+                            id
+                        );
+                    }
+                    exists
+                })
+                .map(|id| CoreId { id })
+                .collect()
+        }
+        None => {
+            let candidates = available.into_iter();
+            if args.no_smt {
+                candidates
+                    .filter(|core| is_primary_thread(core.id))
+                    .take(cores as usize)
+                    .collect()
+            } else {
+                candidates.take(cores as usize).collect()
+            }
+        }
+    };
+
+    // Drop SMT siblings from an explicit list as well.
+    if args.no_smt && args.core_list.is_some() {
+        core_ids.retain(|core| is_primary_thread(core.id));
+    }
+
+    let threads_per_core = args.threads_per_core.unwrap_or(1).max(1) as usize;
+    core_ids
+        .into_iter()
+        .flat_map(|core| {
+            std::iter::repeat(HashThread {
+                core,
+                pin: !args.no_pin,
+            })
+            .take(threads_per_core)
+        })
+        .collect()
+}
+
+/// Returns the number of distinct cores the threads run on.
+pub fn core_count(threads: &[HashThread]) -> u64 {
+    let ids: BTreeSet<usize> = threads.iter().map(|thread| thread.core.id).collect();
+    ids.len() as u64
+}
+
+/// Keeps the threads of the first `cores` distinct cores, so a core list can be trimmed.
+pub fn limit_cores(threads: Vec<HashThread>, cores: u64) -> Vec<HashThread> {
+    let mut kept = BTreeSet::new();
+    threads
+        .into_iter()
+        .filter(|thread| {
+            kept.contains(&thread.core.id) || {
+                kept.len().lt(&(cores as usize)) && kept.insert(thread.core.id)
+            }
+        })
+        .collect()
+}
+
+impl ThreadArgs {
+    /// Fills in options that were not passed as flags from the config file.
+    pub fn with_config(&self, config: &Settings) -> Self {
+        Self {
+            core_list: self.core_list.clone().or(config.core_list.clone()),
+            no_pin: self.no_pin || config.no_pin.unwrap_or(false),
+            no_smt: self.no_smt || config.no_smt.unwrap_or(false),
+            threads_per_core: self.threads_per_core.or(config.threads_per_core),
+        }
+    }
+}
+
+/// Returns the number of logical and physical cores on this machine.
+pub fn core_counts() -> (usize, usize) {
+    let logical = core_affinity::get_core_ids().map_or(0, |ids| ids.len());
+    let physical = core_affinity::get_core_ids().map_or(0, |ids| {
+        ids.into_iter()
+            .filter(|core| is_primary_thread(core.id))
+            .count()
+    });
+    (logical, physical)
+}
+
+/// Formats core ids as a compact core list such as `0,2,4-7`.
+pub fn format_core_list(ids: &[usize]) -> String {
+    let mut ids = ids.to_vec();
+    ids.sort_unstable();
+    ids.dedup();
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    for id in ids {
+        match ranges.last_mut() {
+            Some((_, end)) if end.saturating_add(1).eq(&id) => *end = id,
+            _ => ranges.push((id, id)),
+        }
+    }
+    ranges
+        .into_iter()
+        .map(|(start, end)| {
+            if start.eq(&end) {
+                start.to_string()
+            } else {
+                format!("{}-{}", start, end)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Parses a core list such as `0,2,4-7` into a sorted set of core ids.
+pub fn parse_core_list(list: &str) -> Result<Vec<usize>, String> {
+    let mut ids = BTreeSet::new();
+    for part in list
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+    {
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let start = parse_core_id(start)?;
+                let end = parse_core_id(end)?;
+                if start > end {
+                    return Err(format!("Invalid core range: {}", part));
+                }
+                ids.extend(start..=end);
+            }
+            None => {
+                ids.insert(parse_core_id(part)?);
+            }
+        }
+    }
+    if ids.is_empty() {
+        return Err(format!("Invalid core list: {:?}", list));
+    }
+    Ok(ids.into_iter().collect())
+}
+
+fn parse_core_id(s: &str) -> Result<usize, String> {
+    s.trim()
+        .parse::<usize>()
+        .map_err(|_| format!("Invalid core id: {:?}", s))
+}
+
+/// Returns true if the core is the first hardware thread of its physical core.
+/// Platforms without topology information treat every core as primary.
+fn is_primary_thread(id: usize) -> bool {
+    let path = format!(
+        "/sys/devices/system/cpu/cpu{}/topology/thread_siblings_list",
+        id
+    );
+    match std::fs::read_to_string(path) {
+        Ok(siblings) => parse_core_list(&siblings)
+            .ok()
+            .and_then(|ids| ids.first().copied())
+            .map_or(true, |first| first.eq(&id)),
+        Err(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn threads(ids: &[usize]) -> Vec<HashThread> {
+        ids.iter()
+            .map(|&id| HashThread {
+                core: CoreId { id },
+                pin: true,
+            })
+            .collect()
+    }
+
+    fn ids(threads: &[HashThread]) -> Vec<usize> {
+        threads.iter().map(|thread| thread.core.id).collect()
+    }
+
+    #[test]
+    fn parses_ids_and_ranges() {
+        assert_eq!(parse_core_list("0,2,4-7").unwrap(), vec![0, 2, 4, 5, 6, 7]);
+        assert_eq!(parse_core_list(" 3 , 1 - 2 ").unwrap(), vec![1, 2, 3]);
+        assert_eq!(parse_core_list("5-5").unwrap(), vec![5]);
+    }
+
+    #[test]
+    fn parses_duplicates_once() {
+        assert_eq!(parse_core_list("1,1,0-2,2").unwrap(), vec![0, 1, 2]);
+        assert_eq!(parse_core_list("0-3,2-5").unwrap(), vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn rejects_invalid_core_lists() {
+        assert!(parse_core_list("3-1").is_err());
+        assert!(parse_core_list("-1").is_err());
+        assert!(parse_core_list("1-").is_err());
+        assert!(parse_core_list("a").is_err());
+        assert!(parse_core_list("0,99999999999999999999999").is_err());
+    }
+
+    #[test]
+    fn rejects_empty_core_lists() {
+        assert!(parse_core_list("").is_err());
+        assert!(parse_core_list(" , ,").is_err());
+    }
+
+    #[test]
+    fn formats_compact_core_lists() {
+        assert_eq!(format_core_list(&[0, 2, 4, 5, 6, 7]), "0,2,4-7");
+        assert_eq!(format_core_list(&[7, 5, 6, 5, 0]), "0,5-7");
+        assert_eq!(format_core_list(&[3]), "3");
+        assert_eq!(format_core_list(&[]), "");
+        assert_eq!(
+            format_core_list(&[usize::MAX - 1, usize::MAX]),
+            format!("{}-{}", usize::MAX - 1, usize::MAX)
+        );
+    }
+
+    #[test]
+    fn formatted_core_lists_parse_back() {
+        let list = [1, 2, 3, 8, 10, 11];
+        assert_eq!(parse_core_list(&format_core_list(&list)).unwrap(), list);
+    }
+
+    #[test]
+    fn limits_distinct_cores() {
+        let limited = limit_cores(threads(&[4, 4, 5, 5, 6, 6]), 2);
+        assert_eq!(ids(&limited), vec![4, 4, 5, 5]);
+        assert_eq!(core_count(&limited), 2);
+    }
+
+    #[test]
+    fn limits_beyond_available_cores() {
+        assert_eq!(ids(&limit_cores(threads(&[0, 1]), 8)), vec![0, 1]);
+        assert!(limit_cores(threads(&[0, 1]), 0).is_empty());
+        assert!(limit_cores(vec![], 4).is_empty());
+    }
+}
@@ -1,2 +1,56 @@
-pub const _CU_LIMIT_RESET: u32 = 12_200;
-pub const _CU_LIMIT_MINE: u32 = 3200;
+use ore_api::{consts::NOOP_PROGRAM_ID, instruction::OreInstruction};
+use solana_program::instruction::Instruction;
+use solana_sdk::compute_budget;
+
+use crate::mine::{MINE_DISCRIMINATOR, POOL_PROGRAM_ID};
+
+pub const CU_LIMIT_CLAIM: u32 = 32_000;
+pub const CU_LIMIT_RESET: u32 = 12_200;
+pub const CU_LIMIT_MINE: u32 = 3200;
+/// The pool program's mine instruction, which invokes the ORE mine instruction.
+pub const CU_LIMIT_POOL_MINE: u32 = CU_LIMIT_MINE + CU_LIMIT_INVOKE;
+pub const CU_LIMIT_STAKE: u32 = 32_000;
+pub const CU_LIMIT_CREATE_TOKEN_ACCOUNT: u32 = 30_000;
+pub const CU_LIMIT_TOKEN_TRANSFER: u32 = 6_200;
+pub const CU_LIMIT_DEFAULT: u32 = 500_000;
+
+/// The compute units of builtin instructions such as compute budget, transfers and noops.
+pub const CU_LIMIT_BUILTIN: u32 = 300;
+
+/// The compute units a program spends on its own checks and a cross-program invocation.
+const CU_LIMIT_INVOKE: u32 = 5_000;
+
+/// Returns a compute unit limit for the instructions from per-instruction defaults. Falls back to
+/// `CU_LIMIT_DEFAULT` if any instruction has no known cost.
+pub fn default_cu_limit(ixs: &[Instruction]) -> u32 {
+    let mut total = 0;
+    for ix in ixs {
+        let units = if ix.program_id.eq(&ore_api::ID) {
+            match ix.data.first() {
+                Some(d) if d.eq(&(OreInstruction::Claim as u8)) => CU_LIMIT_CLAIM,
+                Some(d) if d.eq(&(OreInstruction::Mine as u8)) => CU_LIMIT_MINE,
+                Some(d) if d.eq(&(OreInstruction::Reset as u8)) => CU_LIMIT_RESET,
+                Some(d) if d.eq(&(OreInstruction::Stake as u8)) => CU_LIMIT_STAKE,
+                _ => return CU_LIMIT_DEFAULT,
+            }
+        } else if ix.program_id.eq(&POOL_PROGRAM_ID) {
+            if !ix.data.starts_with(&MINE_DISCRIMINATOR) {
+                return CU_LIMIT_DEFAULT;
+            }
+            CU_LIMIT_POOL_MINE
+        } else if ix.program_id.eq(&spl_associated_token_account::id()) {
+            CU_LIMIT_CREATE_TOKEN_ACCOUNT
+        } else if ix.program_id.eq(&spl_token::id()) {
+            CU_LIMIT_TOKEN_TRANSFER
+        } else if ix.program_id.eq(&NOOP_PROGRAM_ID)
+            || ix.program_id.eq(&compute_budget::id())
+            || ix.program_id.eq(&solana_program::system_program::id())
+        {
+            CU_LIMIT_BUILTIN
+        } else {
+            return CU_LIMIT_DEFAULT;
+        };
+        total += units;
+    }
+    total.min(CU_LIMIT_DEFAULT)
+}
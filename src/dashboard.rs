@@ -0,0 +1,487 @@
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Bar, BarChart, BarGroup, Block, Borders, Paragraph, Row, Sparkline, Table},
+    Frame, Terminal,
+};
+use solana_program::{native_token::lamports_to_sol, pubkey::Pubkey};
+use solana_sdk::signature::Signature;
+
+use crate::{
+    control::{request_shutdown, MineControl},
+    exit_with_error,
+    telemetry::Telemetry,
+    utils::amount_u64_to_f64,
+};
+
+/// How often the dashboard redraws while waiting for key presses.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The number of recent submissions shown.
+const MAX_SUBMISSIONS: usize = 10;
+
+/// The number of log lines kept.
+const MAX_LOG_LINES: usize = 100;
+
+/// The number of rounds of stake history kept for the trend.
+const MAX_ROUNDS: usize = 240;
+
+/// The number of difficulty levels shown in the histogram, ending at the best difficulty.
+const HISTOGRAM_LEVELS: usize = 12;
+
+/// A transaction the mining loop submitted.
+pub struct Submission {
+    /// The signature of the transaction, or none if it failed before landing.
+    pub signature: Option<Signature>,
+    pub difficulty: u32,
+    pub status: String,
+    pub latency: Duration,
+}
+
+/// What the mining loop is doing.
+#[derive(Clone, Copy, Default)]
+enum Phase {
+    #[default]
+    Starting,
+    Hashing {
+        cutoff: Instant,
+    },
+    Submitting,
+}
+
+#[derive(Default)]
+struct DashboardState {
+    signer: String,
+    phase: Phase,
+    /// The cores the current round hashes with.
+    cores: u64,
+    min_difficulty: u32,
+    /// The hashing stats of the current round, or of the last one while submitting.
+    telemetry: Option<Arc<Telemetry>>,
+    stakes: VecDeque<u64>,
+    multipliers: VecDeque<f64>,
+    submissions: VecDeque<Submission>,
+    tip: u64,
+    priority_fee: u64,
+    log: VecDeque<String>,
+    /// Set once the terminal is handed back, so nothing is drawn over later output.
+    closed: bool,
+}
+
+/// A full-screen view of the mining loop. The terminal is drawn from its own thread, so keys
+/// stay responsive while the loop hashes and submits.
+pub struct Dashboard {
+    state: Arc<Mutex<DashboardState>>,
+}
+
+impl Dashboard {
+    /// Takes over the terminal and starts drawing. Keys pause and resume hashing and change the
+    /// core count through `control`.
+    pub fn start(signer: Pubkey, control: Arc<MineControl>) -> io::Result<Self> {
+        let mut terminal = take_over_terminal()?;
+
+        // Anything printed with colors is shown as plain text in the log
+        colored::control::set_override(false);
+
+        let state = Arc::new(Mutex::new(DashboardState {
+            signer: signer.to_string(),
+            ..Default::default()
+        }));
+        let thread_state = Arc::clone(&state);
+        std::thread::spawn(move || {
+            if let Err(err) = run(&mut terminal, &thread_state, &control) {
+                restore_terminal();
+                exit_with_error(&format!("Terminal error: {}", err));
+            }
+        });
+        Ok(Self { state })
+    }
+
+    /// Adds a line to the log.
+    pub fn log(&self, line: impl Into<String>) {
+        let mut state = self.state.lock().unwrap();
+        state.log.push_back(line.into());
+        if state.log.len().gt(&MAX_LOG_LINES) {
+            state.log.pop_front();
+        }
+    }
+
+    /// Records the stake and multiplier of the proof at the start of a round.
+    pub fn set_stake(&self, balance: u64, multiplier: f64) {
+        let mut state = self.state.lock().unwrap();
+        state.stakes.push_back(balance);
+        state.multipliers.push_back(multiplier);
+        if state.stakes.len().gt(&MAX_ROUNDS) {
+            state.stakes.pop_front();
+            state.multipliers.pop_front();
+        }
+    }
+
+    /// Records the number of cores the mining loop hashes with.
+    pub fn set_cores(&self, cores: u64) {
+        self.state.lock().unwrap().cores = cores;
+    }
+
+    /// Shows a round that hashes until the cutoff, drawing its telemetry as it changes.
+    pub fn start_round(&self, cutoff: Instant, min_difficulty: u32, telemetry: Arc<Telemetry>) {
+        let mut state = self.state.lock().unwrap();
+        state.phase = Phase::Hashing { cutoff };
+        state.min_difficulty = min_difficulty;
+        state.telemetry = Some(telemetry);
+    }
+
+    /// Shows that the round finished and its solution is being submitted.
+    pub fn finish_round(&self) {
+        self.state.lock().unwrap().phase = Phase::Submitting;
+    }
+
+    /// Records a submission along with the tip and priority fee it paid.
+    pub fn add_submission(&self, submission: Submission, tip: u64, priority_fee: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.submissions.push_front(submission);
+        state.submissions.truncate(MAX_SUBMISSIONS);
+        state.tip = tip;
+        state.priority_fee = priority_fee;
+    }
+
+    /// Hands the terminal back, so errors can be printed before exiting.
+    pub fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        restore_terminal();
+        colored::control::unset_override();
+    }
+}
+
+/// Prints a line, or adds it to the dashboard log while the dashboard is open.
+pub fn report(dashboard: Option<&Dashboard>, line: impl Into<String>) {
+    match dashboard {
+        Some(dashboard) => dashboard.log(line),
+        None => println!("{}", line.into()),
+    }
+}
+
+/// Enters raw mode and the alternate screen for a full-screen view, and restores the terminal
+/// if that fails or the program later panics. Call `restore_terminal` when done.
+pub fn take_over_terminal() -> io::Result<Terminal<CrosstermBackend<io::Stdout>>> {
+    let terminal = enable_raw_mode()
+        .and_then(|_| execute!(io::stdout(), EnterAlternateScreen))
+        .and_then(|_| Terminal::new(CrosstermBackend::new(io::stdout())));
+    if terminal.is_err() {
+        restore_terminal();
+        return terminal;
+    }
+
+    // Leave the terminal usable if the program panics
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        hook(info);
+    }));
+    terminal
+}
+
+/// Leaves the alternate screen and raw mode, handing the terminal back to the shell.
+pub fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(io::stdout(), LeaveAlternateScreen);
+}
+
+/// Draws the dashboard and handles keys until the mining loop closes it.
+fn run(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    state: &Mutex<DashboardState>,
+    control: &MineControl,
+) -> io::Result<()> {
+    loop {
+        {
+            let state = state.lock().unwrap();
+            if state.closed {
+                return Ok(());
+            }
+            terminal.draw(|frame| draw_dashboard(frame, &state, control))?;
+        }
+
+        if !event::poll(REDRAW_INTERVAL)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind.ne(&KeyEventKind::Press) {
+            continue;
+        }
+        match key.code {
+            // Raw mode turns Ctrl+C into a key press rather than SIGINT
+            KeyCode::Char('q') | KeyCode::Esc => request_shutdown(control, true),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                request_shutdown(control, true)
+            }
+            KeyCode::Char('p') | KeyCode::Char(' ') => control.set_paused(!control.is_paused()),
+            KeyCode::Char('+') | KeyCode::Char('=') | KeyCode::Up => {
+                control.set_cores(control.cores().saturating_add(1));
+            }
+            KeyCode::Char('-') | KeyCode::Down => {
+                control.set_cores(control.cores().saturating_sub(1));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn draw_dashboard(frame: &mut Frame, state: &DashboardState, control: &MineControl) {
+    let [header_area, top_area, middle_area, submissions_area, log_area, footer_area] =
+        Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Length(10),
+            Constraint::Length(6),
+            Constraint::Length(MAX_SUBMISSIONS as u16 + 3),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.size());
+    let [hashrate_area, histogram_area] =
+        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .areas(top_area);
+    let [stake_area, fees_area] =
+        Layout::horizontal([Constraint::Percentage(70), Constraint::Percentage(30)])
+            .areas(middle_area);
+
+    draw_header(frame, header_area, state, control);
+    draw_hashrates(frame, hashrate_area, state);
+    draw_histogram(frame, histogram_area, state);
+    draw_stake(frame, stake_area, state);
+    draw_fees(frame, fees_area, state);
+    draw_submissions(frame, submissions_area, state);
+
+    let lines = log_area.height.saturating_sub(2) as usize;
+    let log: Vec<Line> = state
+        .log
+        .iter()
+        .skip(state.log.len().saturating_sub(lines))
+        .map(|line| Line::from(line.as_str()))
+        .collect();
+    frame.render_widget(
+        Paragraph::new(log).block(Block::default().borders(Borders::ALL).title(" Log ")),
+        log_area,
+    );
+
+    let footer =
+        "p pause/resume  +/- cores  q quit after the current submission (twice to exit now)";
+    frame.render_widget(Paragraph::new(Line::from(footer)), footer_area);
+}
+
+fn draw_header(frame: &mut Frame, area: Rect, state: &DashboardState, control: &MineControl) {
+    let (status, color) = match (control.is_paused(), state.phase) {
+        _ if control.is_shutting_down() => (
+            "Shutting down after the current submission".to_string(),
+            Color::Red,
+        ),
+        (true, _) => ("Paused".to_string(), Color::Yellow),
+        (false, Phase::Starting) => ("Starting".to_string(), Color::Gray),
+        (false, Phase::Hashing { cutoff }) => (
+            format!(
+                "Hashing, cutoff in {}",
+                format_duration(cutoff.saturating_duration_since(Instant::now()))
+            ),
+            Color::Green,
+        ),
+        (false, Phase::Submitting) => ("Submitting".to_string(), Color::Cyan),
+    };
+    let mut cores = format!("Cores: {}", state.cores);
+    if control.cores().ne(&state.cores) && state.cores.gt(&0) {
+        cores = format!("{} ({} next round)", cores, control.cores());
+    }
+    let line = Line::from(format!(
+        "{}  |  {}  |  Best difficulty: {} (min {})",
+        status,
+        cores,
+        state
+            .telemetry
+            .as_ref()
+            .map_or(0, |telemetry| telemetry.best_difficulty()),
+        state.min_difficulty
+    ));
+    let header = Paragraph::new(line)
+        .style(Style::default().fg(color))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!(" Mining with {} ", state.signer)),
+        );
+    frame.render_widget(header, area);
+}
+
+fn draw_hashrates(frame: &mut Frame, area: Rect, state: &DashboardState) {
+    let cores = state
+        .telemetry
+        .as_ref()
+        .map_or(vec![], |telemetry| telemetry.cores());
+    let total: f64 = cores.iter().map(|stats| stats.hashrate).sum();
+    let rows = cores.iter().map(|stats| {
+        Row::new(vec![
+            stats.core.to_string(),
+            format!("{:.0}", stats.hashrate),
+            stats.hashes.to_string(),
+            stats.best_difficulty.to_string(),
+        ])
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(6),
+            Constraint::Length(10),
+            Constraint::Length(12),
+            Constraint::Length(6),
+        ],
+    )
+    .header(
+        Row::new(vec!["core", "H/s", "hashes", "best"])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" Hashrate: {:.0} H/s ", total)),
+    );
+    frame.render_widget(table, area);
+}
+
+fn draw_histogram(frame: &mut Frame, area: Rect, state: &DashboardState) {
+    let difficulties = state
+        .telemetry
+        .as_ref()
+        .map_or(vec![], |telemetry| telemetry.difficulties());
+    // Show the hardest levels reached, since each easier level has about twice as many hashes
+    let top = difficulties
+        .iter()
+        .rposition(|count| count.gt(&0))
+        .unwrap_or(0);
+    let start = (top + 1).saturating_sub(HISTOGRAM_LEVELS);
+    let bars: Vec<Bar> = difficulties
+        .iter()
+        .enumerate()
+        .take(top + 1)
+        .skip(start)
+        .map(|(difficulty, count)| {
+            let style = if (difficulty as u32).ge(&state.min_difficulty) {
+                Style::default().fg(Color::Green)
+            } else {
+                Style::default().fg(Color::DarkGray)
+            };
+            Bar::default()
+                .label(Line::from(difficulty.to_string()))
+                .value(*count)
+                .style(style)
+        })
+        .collect();
+    let chart = BarChart::default()
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Hashes by difficulty "),
+        )
+        .data(BarGroup::default().bars(&bars))
+        .bar_width(4)
+        .bar_gap(1);
+    frame.render_widget(chart, area);
+}
+
+fn draw_stake(frame: &mut Frame, area: Rect, state: &DashboardState) {
+    let (Some(first), Some(last)) = (state.stakes.front(), state.stakes.back()) else {
+        frame.render_widget(
+            Block::default().borders(Borders::ALL).title(" Stake "),
+            area,
+        );
+        return;
+    };
+    let multiplier = state.multipliers.back().copied().unwrap_or(1.0);
+    let multiplier_change = multiplier - state.multipliers.front().copied().unwrap_or(1.0);
+    let title = format!(
+        " Stake: {} ORE ({:+}) | Multiplier: {:.4}x ({:+.4}) ",
+        amount_u64_to_f64(*last),
+        amount_u64_to_f64(*last) - amount_u64_to_f64(*first),
+        multiplier,
+        multiplier_change
+    );
+    // Plot the change, since it is small next to the stake itself
+    let min = state.stakes.iter().min().copied().unwrap_or(0);
+    let data: Vec<u64> = state.stakes.iter().map(|stake| stake - min).collect();
+    let sparkline = Sparkline::default()
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .data(
+            &data[data
+                .len()
+                .saturating_sub(area.width.saturating_sub(2) as usize)..],
+        )
+        .style(Style::default().fg(Color::Yellow));
+    frame.render_widget(sparkline, area);
+}
+
+fn draw_fees(frame: &mut Frame, area: Rect, state: &DashboardState) {
+    let lines = vec![
+        Line::from(format!("Jito tip: {} SOL", lamports_to_sol(state.tip))),
+        Line::from(format!("Priority fee: {} µlamports/CU", state.priority_fee)),
+    ];
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(" Fees ")),
+        area,
+    );
+}
+
+fn draw_submissions(frame: &mut Frame, area: Rect, state: &DashboardState) {
+    let rows = state.submissions.iter().map(|submission| {
+        let style = if submission.signature.is_some() {
+            Style::default()
+        } else {
+            Style::default().fg(Color::Red)
+        };
+        Row::new(vec![
+            submission
+                .signature
+                .map_or("-".to_string(), |signature| signature.to_string()),
+            submission.difficulty.to_string(),
+            format!("{:.1}s", submission.latency.as_secs_f64()),
+            submission.status.clone(),
+        ])
+        .style(style)
+    });
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Min(20),
+            Constraint::Length(10),
+            Constraint::Length(9),
+            Constraint::Length(40),
+        ],
+    )
+    .header(
+        Row::new(vec!["signature", "difficulty", "latency", "status"])
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(" Recent submissions "),
+    );
+    frame.render_widget(table, area);
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
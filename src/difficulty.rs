@@ -0,0 +1,84 @@
+/// The highest difficulty tracked individually in hash distributions. Harder hashes are
+/// counted in the last bucket.
+pub const MAX_TRACKED_DIFFICULTY: usize = 63;
+
+/// The probability that a single drillx hash has at least the given difficulty.
+/// Hashes are uniformly distributed, so each leading zero bit halves the odds.
+pub fn hash_probability(difficulty: u32) -> f64 {
+    0.5f64.powi(difficulty as i32)
+}
+
+/// The probability that the best of `hashes` hashes has at least the given difficulty.
+pub fn best_probability(difficulty: u32, hashes: f64) -> f64 {
+    if hashes <= 0.0 {
+        return 0.0;
+    }
+    // 1 - (1 - p)^n, computed in log space to stay accurate for tiny p
+    -f64::exp_m1(hashes * f64::ln_1p(-hash_probability(difficulty)))
+}
+
+/// The probability that the best of `hashes` hashes has exactly the given difficulty.
+pub fn best_probability_exact(difficulty: u32, hashes: f64) -> f64 {
+    best_probability(difficulty, hashes) - best_probability(difficulty + 1, hashes)
+}
+
+/// The expected difficulty of the best of `hashes` hashes.
+pub fn expected_best_difficulty(hashes: f64) -> f64 {
+    (1..=MAX_TRACKED_DIFFICULTY as u32)
+        .map(|difficulty| best_probability(difficulty, hashes))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() <= expected.abs() * 1e-9,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn halves_hash_probability_per_difficulty() {
+        assert_eq!(hash_probability(0), 1.0);
+        assert_eq!(hash_probability(1), 0.5);
+        assert_eq!(hash_probability(10), 1.0 / 1024.0);
+    }
+
+    #[test]
+    fn best_probability_of_few_hashes() {
+        assert_eq!(best_probability(1, 0.0), 0.0);
+        assert_close(best_probability(0, 5.0), 1.0);
+        assert_close(best_probability(1, 1.0), 0.5);
+        assert_close(best_probability(1, 2.0), 0.75);
+        assert_close(best_probability_exact(1, 1.0), 0.25);
+    }
+
+    #[test]
+    fn best_probability_stays_accurate_for_hard_difficulties() {
+        assert_close(best_probability(60, 1.0), 0.5f64.powi(60));
+        assert_close(best_probability(60, 1000.0), 1000.0 * 0.5f64.powi(60));
+    }
+
+    #[test]
+    fn exact_probabilities_sum_to_one() {
+        for hashes in [1.0, 1_000.0, 1e9] {
+            let total: f64 = (0..=MAX_TRACKED_DIFFICULTY as u32)
+                .map(|difficulty| best_probability_exact(difficulty, hashes))
+                .sum();
+            assert_close(total, 1.0);
+        }
+    }
+
+    #[test]
+    fn expected_best_difficulty_grows_with_log_hashes() {
+        assert_close(expected_best_difficulty(1.0), 1.0 - 0.5f64.powi(63));
+        let expected = expected_best_difficulty(2f64.powi(20));
+        assert!((20.0..21.0).contains(&expected), "{}", expected);
+        assert!(expected_best_difficulty(2f64.powi(21)) > expected + 0.9);
+    }
+}
@@ -1,6 +1,199 @@
+use colored::*;
+use ore_api::error::OreError;
+use solana_client::{
+    client_error::{ ClientError, ClientErrorKind },
+    rpc_request::{ RpcError, RpcResponseErrorData },
+};
+use solana_program::instruction::{ Instruction, InstructionError };
+use solana_sdk::transaction::TransactionError;
+
+/// The number of program log lines printed with a failed transaction.
+const LOG_EXCERPT_LINES: usize = 5;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("reqwest")] Reqwest(#[from] reqwest::Error),
     #[error("solana rpc client")] SolanaRpcClient(#[from] solana_client::client_error::ClientError),
     #[error("solana program")] SolanaProgram(#[from] solana_program::program_error::ProgramError),
 }
+
+/// Why `send_and_confirm` failed to land a transaction.
+#[derive(Debug, thiserror::Error)]
+pub enum SendError {
+    /// The ORE program rejected the transaction, directly or through a CPI.
+    #[error("{error}")] Ore {
+        error: OreError,
+        logs: Vec<String>,
+    },
+
+    /// The transaction failed for a reason other than an ORE program error.
+    #[error("{error}")] Transaction {
+        error: TransactionError,
+        logs: Vec<String>,
+    },
+
+    #[error("{0}")] Other(String),
+}
+
+impl SendError {
+    /// Decodes a transaction error, mapping custom program errors raised by the ORE program to
+    /// `OreError`. `ixs` are the instructions of the failed transaction, in order.
+    pub fn from_transaction_error(
+        error: TransactionError,
+        ixs: &[Instruction],
+        logs: Vec<String>
+    ) -> Self {
+        if let TransactionError::InstructionError(index, InstructionError::Custom(code)) = &error {
+            let program_id = ixs.get(*index as usize).map(|ix| ix.program_id);
+            // ORE errors raised through a CPI surface on the calling instruction
+            let ore_failed =
+                program_id.eq(&Some(ore_api::ID)) ||
+                logs
+                    .iter()
+                    .any(|line|
+                        line.starts_with(
+                            &format!("Program {} failed: custom program error", ore_api::ID)
+                        )
+                    );
+            if let Some(ore_error) = ore_error(*code).filter(|_| ore_failed) {
+                return SendError::Ore { error: ore_error, logs };
+            }
+        }
+        SendError::Transaction { error, logs }
+    }
+
+    /// Decodes a client error, using the preflight simulation logs if the RPC returned them.
+    pub fn from_client_error(error: ClientError, ixs: &[Instruction]) -> Self {
+        let logs = match error.kind() {
+            ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                data: RpcResponseErrorData::SendTransactionPreflightFailure(result),
+                ..
+            }) => result.logs.clone().unwrap_or_default(),
+            _ => vec![],
+        };
+        match error.get_transaction_error() {
+            Some(tx_error) => Self::from_transaction_error(tx_error, ixs, logs),
+            None => SendError::Other(error.to_string()),
+        }
+    }
+
+    /// Returns the ORE program error, if that is why the transaction failed.
+    pub fn ore_error(&self) -> Option<OreError> {
+        match self {
+            SendError::Ore { error, .. } => Some(*error),
+            _ => None,
+        }
+    }
+
+    /// Prints the error along with the last program log lines.
+    pub fn print(&self) {
+        eprintln!("{} {}", "ERROR".bold().red(), self);
+        let logs = match self {
+            SendError::Ore { logs, .. } | SendError::Transaction { logs, .. } => logs.as_slice(),
+            SendError::Other(_) => &[],
+        };
+        let excerpt: Vec<&String> = logs
+            .iter()
+            .filter(|line| line.starts_with("Program log:") || line.contains(" failed: "))
+            .collect();
+        for line in &excerpt[excerpt.len().saturating_sub(LOG_EXCERPT_LINES)..] {
+            eprintln!("  {}", line.dimmed());
+        }
+    }
+}
+
+/// Maps a custom program error code to the ORE error it stands for.
+pub fn ore_error(code: u32) -> Option<OreError> {
+    match code {
+        0 => Some(OreError::NeedsReset),
+        1 => Some(OreError::HashInvalid),
+        2 => Some(OreError::HashTooEasy),
+        3 => Some(OreError::ClaimTooLarge),
+        4 => Some(OreError::ClockInvalid),
+        5 => Some(OreError::Spam),
+        6 => Some(OreError::MaxSupply),
+        7 => Some(OreError::AuthFailed),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::pubkey::Pubkey;
+
+    const ORE_ERRORS: [OreError; 8] = [
+        OreError::NeedsReset,
+        OreError::HashInvalid,
+        OreError::HashTooEasy,
+        OreError::ClaimTooLarge,
+        OreError::ClockInvalid,
+        OreError::Spam,
+        OreError::MaxSupply,
+        OreError::AuthFailed,
+    ];
+
+    fn ix(program_id: Pubkey) -> Instruction {
+        Instruction::new_with_bytes(program_id, &[], vec![])
+    }
+
+    fn custom_error(index: u8, code: u32) -> TransactionError {
+        TransactionError::InstructionError(index, InstructionError::Custom(code))
+    }
+
+    #[test]
+    fn decodes_every_ore_error_code() {
+        for error in ORE_ERRORS {
+            assert_eq!(ore_error(error.into()), Some(error));
+        }
+        assert_eq!(ore_error(8), None);
+        assert_eq!(ore_error(u32::MAX), None);
+    }
+
+    #[test]
+    fn decodes_errors_of_ore_instructions() {
+        let ixs = [ix(Pubkey::new_unique()), ix(ore_api::ID)];
+        for error in ORE_ERRORS {
+            let decoded = SendError::from_transaction_error(
+                custom_error(1, error.into()),
+                &ixs,
+                vec![]
+            );
+            assert_eq!(decoded.ore_error(), Some(error));
+        }
+    }
+
+    #[test]
+    fn decodes_ore_errors_raised_through_a_cpi() {
+        let ixs = [ix(Pubkey::new_unique())];
+        let logs = vec![format!("Program {} failed: custom program error: 0x5", ore_api::ID)];
+        let decoded = SendError::from_transaction_error(custom_error(0, 5), &ixs, logs);
+        assert_eq!(decoded.ore_error(), Some(OreError::Spam));
+    }
+
+    #[test]
+    fn keeps_errors_of_other_programs() {
+        let ixs = [ix(Pubkey::new_unique()), ix(ore_api::ID)];
+        let decoded = SendError::from_transaction_error(custom_error(0, 5), &ixs, vec![]);
+        assert!(matches!(decoded, SendError::Transaction { .. }));
+    }
+
+    #[test]
+    fn keeps_unknown_and_non_custom_errors() {
+        let ixs = [ix(ore_api::ID)];
+        let decoded = SendError::from_transaction_error(custom_error(0, 8), &ixs, vec![]);
+        assert!(matches!(decoded, SendError::Transaction { .. }));
+        let decoded = SendError::from_transaction_error(
+            TransactionError::InstructionError(0, InstructionError::InvalidAccountData),
+            &ixs,
+            vec![]
+        );
+        assert!(matches!(decoded, SendError::Transaction { .. }));
+        let decoded = SendError::from_transaction_error(
+            TransactionError::BlockhashNotFound,
+            &ixs,
+            vec![]
+        );
+        assert!(decoded.ore_error().is_none());
+    }
+}
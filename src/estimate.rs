@@ -0,0 +1,262 @@
+use std::time::{Duration, Instant};
+
+use colored::*;
+use ore_api::{
+    consts::{EPOCH_DURATION, ONE_MINUTE},
+    state::{Config, Proof},
+};
+use ore_utils::AccountDeserialize;
+use serde::Serialize;
+use solana_program::native_token::LAMPORTS_PER_SOL;
+
+use crate::{
+    args::{EstimateArgs, OutputFormat, ThreadArgs},
+    cores::hash_threads,
+    cu_limits::{CU_LIMIT_BUILTIN, CU_LIMIT_POOL_MINE},
+    difficulty::{best_probability_exact, MAX_TRACKED_DIFFICULTY},
+    hash_pool::HashPool,
+    mine::{expected_reward, POOL_AUTHORITY},
+    send_and_confirm::PRIORITY_FEE,
+    utils::{amount_f64_to_u64, amount_u64_to_f64, calculate_multiplier, get_config, proof_pubkey},
+    Miner,
+};
+
+/// The base fee paid per transaction signature, in lamports.
+const SIGNATURE_FEE: u64 = 5000;
+
+/// The number of seconds in a day.
+const ONE_DAY: i64 = 86_400;
+
+/// The compute unit limit of a submission: the proof authentication and the pool mine
+/// instruction, sized like real submissions.
+const MINE_CU_LIMIT: u32 = CU_LIMIT_BUILTIN + CU_LIMIT_POOL_MINE;
+
+/// How long to wait for the first tip stream update.
+const TIP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often to check for the first tip stream update.
+const TIP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Serialize)]
+pub struct EstimateReport {
+    pub hashes_per_sec: f64,
+    pub stake: f64,
+    pub multiplier: f64,
+    pub min_difficulty: u64,
+    pub expected_ore_per_round: f64,
+    pub expected_ore_per_epoch: f64,
+    pub expected_ore_per_day: f64,
+    pub fee_sol_per_round: f64,
+    pub fee_sol_per_day: f64,
+    pub ore_price: Option<f64>,
+    pub net_ore_per_day: Option<f64>,
+    pub net_sol_per_day: Option<f64>,
+}
+
+impl Miner {
+    pub async fn estimate(&self, args: EstimateArgs) {
+        // Measure or read the hashrate
+        let hashes_per_sec = match args.hashrate {
+            Some(hashrate) => hashrate,
+            None => {
+                let cores = self.settings.cores.unwrap_or(1);
+                let threads =
+                    hash_threads(cores, &ThreadArgs::default().with_config(&self.settings));
+                let pool = HashPool::new(&threads);
+                Self::benchmark_pool(&pool, args.benchmark.unwrap_or_default())
+                    .await
+                    .hashes_per_sec
+            }
+        };
+
+        // Fetch the program config and the stake of the proof mined to
+        let config = get_config(&self.rpc_client).await;
+        let stake = match args.stake {
+            Some(stake) => amount_f64_to_u64(stake),
+            None => self.pool_proof_balance().await,
+        };
+        let multiplier = calculate_multiplier(stake, config.top_balance);
+
+        let round_hashes =
+            hashes_per_sec * ONE_MINUTE.saturating_sub(args.buffer_time as i64) as f64;
+        let expected_ore_per_round =
+            amount_u64_to_f64(expected_reward_per_round(&config, stake, round_hashes) as u64);
+
+        // Subtract the cost of landing each submission
+        let priority_fee = args.priority_fee.unwrap_or(PRIORITY_FEE);
+        let jito_tip = match args.jito_tip {
+            Some(jito_tip) => jito_tip,
+            None => self.live_tip().await,
+        };
+        let fee_lamports_per_round = SIGNATURE_FEE
+            + (MINE_CU_LIMIT as u64).saturating_mul(priority_fee) / 1_000_000
+            + jito_tip;
+        let fee_sol_per_round = fee_lamports_per_round as f64 / LAMPORTS_PER_SOL as f64;
+
+        let rounds_per_epoch = (EPOCH_DURATION / ONE_MINUTE) as f64;
+        let rounds_per_day = (ONE_DAY / ONE_MINUTE) as f64;
+        let expected_ore_per_day = expected_ore_per_round * rounds_per_day;
+        let fee_sol_per_day = fee_sol_per_round * rounds_per_day;
+        let report = EstimateReport {
+            hashes_per_sec,
+            stake: amount_u64_to_f64(stake),
+            multiplier,
+            min_difficulty: config.min_difficulty,
+            expected_ore_per_round,
+            expected_ore_per_epoch: expected_ore_per_round * rounds_per_epoch,
+            expected_ore_per_day,
+            fee_sol_per_round,
+            fee_sol_per_day,
+            ore_price: args.ore_price,
+            net_ore_per_day: args
+                .ore_price
+                .map(|price| expected_ore_per_day - fee_sol_per_day / price),
+            net_sol_per_day: args
+                .ore_price
+                .map(|price| expected_ore_per_day * price - fee_sol_per_day),
+        };
+
+        match args
+            .format
+            .or(self.settings.format)
+            .unwrap_or(OutputFormat::Text)
+        {
+            OutputFormat::Json => {
+                println!("{}", serde_json::to_string_pretty(&report).unwrap());
+            }
+            OutputFormat::Text => print_report(&report),
+        }
+    }
+
+    /// Returns the live Jito tip, waiting for the first tip stream update if Jito is enabled.
+    /// Returns 0 if Jito is disabled or no update arrives in time.
+    async fn live_tip(&self) -> u64 {
+        if self.tip_settings.is_none() {
+            return 0;
+        }
+        let started = Instant::now();
+        loop {
+            let tip = *self.tip.read().unwrap();
+            if tip.gt(&0) || started.elapsed().ge(&TIP_TIMEOUT) {
+                return tip;
+            }
+            tokio::time::sleep(TIP_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Returns the stake of the pool proof that `ore mine` submits to, or 0 if it is missing.
+    async fn pool_proof_balance(&self) -> u64 {
+        let address = proof_pubkey(POOL_AUTHORITY);
+        match self.rpc_client.get_account_data(&address).await {
+            Ok(data) => Proof::try_from_bytes(&data).map_or(0, |proof| proof.balance),
+            Err(_) => 0,
+        }
+    }
+}
+
+/// Sums the reward at each difficulty weighted by its probability of being the best of the
+/// round's hashes, in grains.
+fn expected_reward_per_round(config: &Config, stake: u64, round_hashes: f64) -> f64 {
+    (config.min_difficulty as u32..=MAX_TRACKED_DIFFICULTY as u32)
+        .map(|difficulty| {
+            best_probability_exact(difficulty, round_hashes)
+                * expected_reward(config, stake, difficulty) as f64
+        })
+        .sum()
+}
+
+fn print_report(report: &EstimateReport) {
+    println!(
+        "Hashpower: {:.0} H/sec\nStake: {} ORE\nMultiplier: {:.4}x\nMin difficulty: {}\n",
+        report.hashes_per_sec, report.stake, report.multiplier, report.min_difficulty
+    );
+    println!(
+        "Expected rewards:\n  Per round: {:.11} ORE\n  Per epoch: {:.11} ORE\n  Per day:   {:.11} ORE\n",
+        report.expected_ore_per_round, report.expected_ore_per_epoch, report.expected_ore_per_day
+    );
+    println!(
+        "Fees:\n  Per round: {:.9} SOL\n  Per day:   {:.9} SOL\n",
+        report.fee_sol_per_round, report.fee_sol_per_day
+    );
+    match (report.net_ore_per_day, report.net_sol_per_day) {
+        (Some(net_ore), Some(net_sol)) => {
+            let line = format!(
+                "Net profit per day: {:.11} ORE ({:.9} SOL)",
+                net_ore, net_sol
+            );
+            if net_sol >= 0.0 {
+                println!("{}", line.bold().green());
+            } else {
+                println!("{}", line.bold().red());
+            }
+        }
+        _ => println!("Pass --ore-price to compare rewards against fees."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOP_BALANCE: u64 = 1_000_000;
+
+    fn config(min_difficulty: u64) -> Config {
+        Config {
+            base_reward_rate: 1,
+            last_reset_at: 0,
+            min_difficulty,
+            top_balance: TOP_BALANCE,
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() <= expected.abs() * 1e-9,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn no_hashes_earn_nothing() {
+        assert_eq!(expected_reward_per_round(&config(1), 0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn weighs_rewards_by_best_hash_probability() {
+        // A single hash is exactly difficulty d with odds 2^-(d+1) and earns 2^(d-1), so every
+        // difficulty from 1 to 63 adds a quarter
+        assert_close(expected_reward_per_round(&config(1), 0, 1.0), 63.0 * 0.25);
+    }
+
+    #[test]
+    fn ignores_difficulties_below_the_minimum() {
+        // Only a best hash of difficulty 63 counts, with odds 2^-64
+        assert_close(
+            expected_reward_per_round(&config(63), 0, 1.0),
+            0.5f64.powi(64),
+        );
+    }
+
+    #[test]
+    fn applies_the_stake_multiplier() {
+        let unstaked = expected_reward_per_round(&config(1), 0, 1.0);
+        assert_close(
+            expected_reward_per_round(&config(1), TOP_BALANCE, 1.0),
+            2.0 * unstaked,
+        );
+        assert_close(
+            expected_reward_per_round(&config(1), 4 * TOP_BALANCE, 1.0),
+            2.0 * unstaked,
+        );
+    }
+
+    #[test]
+    fn more_hashes_earn_more() {
+        let config = config(8);
+        let slow = expected_reward_per_round(&config, 0, 1e4);
+        let fast = expected_reward_per_round(&config, 0, 1e6);
+        assert!(fast > slow);
+    }
+}
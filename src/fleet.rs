@@ -0,0 +1,408 @@
+use std::{
+    io,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use clap::ValueEnum;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ore_api::{
+    consts::CONFIG_ADDRESS,
+    state::{Config, Proof},
+};
+use ore_utils::AccountDeserialize;
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Paragraph, Row, Table},
+    Frame, Terminal,
+};
+use serde::Serialize;
+use solana_program::{clock::Clock, pubkey::Pubkey, sysvar};
+
+use crate::{
+    args::{FleetArgs, FleetColumn},
+    dashboard::{restore_terminal, take_over_terminal},
+    exit_with_error,
+    utils::{amount_u64_to_f64, calculate_multiplier, format_age, proof_pubkey},
+    Miner,
+};
+
+/// The most accounts `getMultipleAccounts` returns per request.
+const CHUNK_SIZE: usize = 100;
+
+/// How long to wait for a key press before redrawing.
+const INPUT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The state of one authority's proof.
+#[derive(Clone, Debug, Serialize)]
+struct FleetRow {
+    authority: String,
+    stake: f64,
+    multiplier: f64,
+    /// Seconds since the last hash, or none if the authority has no proof.
+    last_hash_age: Option<i64>,
+    total_hashes: u64,
+    total_rewards: f64,
+    down: bool,
+}
+
+impl Miner {
+    pub async fn fleet(&self, args: FleetArgs) {
+        let authorities =
+            read_authorities(&args.authorities).unwrap_or_else(|err| exit_with_error(&err));
+        let down_after = Duration::from_secs(args.down_after.saturating_mul(60));
+        if let Some(path) = &args.export {
+            // Check the format before fetching anything
+            let format = export_format(path).unwrap_or_else(|err| exit_with_error(&err));
+            let mut rows = self
+                .fetch_fleet(&authorities, down_after)
+                .await
+                .unwrap_or_else(|err| exit_with_error(&err));
+            let sort = args.sort.unwrap_or(FleetColumn::Stake);
+            sort_rows(&mut rows, sort, default_descending(sort));
+            export(path, format, &rows).unwrap_or_else(|err| exit_with_error(&err));
+            println!("Wrote {} authorities to {}", rows.len(), path);
+            return;
+        }
+        if let Err(err) = self.fleet_table(&authorities, down_after, &args).await {
+            exit_with_error(&format!("Terminal error: {}", err));
+        }
+    }
+
+    /// Fetches the proof of every authority in batches.
+    async fn fetch_fleet(
+        &self,
+        authorities: &[Pubkey],
+        down_after: Duration,
+    ) -> Result<Vec<FleetRow>, String> {
+        let config_data = self
+            .rpc_client
+            .get_account_data(&CONFIG_ADDRESS)
+            .await
+            .map_err(|err| format!("Failed to get config account: {}", err))?;
+        let config = *Config::try_from_bytes(&config_data)
+            .map_err(|err| format!("Failed to parse config account: {}", err))?;
+        let clock_data = self
+            .rpc_client
+            .get_account_data(&sysvar::clock::ID)
+            .await
+            .map_err(|err| format!("Failed to get clock: {}", err))?;
+        let clock = bincode::deserialize::<Clock>(&clock_data)
+            .map_err(|err| format!("Failed to parse clock: {}", err))?;
+
+        let mut rows = Vec::with_capacity(authorities.len());
+        for chunk in authorities.chunks(CHUNK_SIZE) {
+            let addresses: Vec<Pubkey> = chunk
+                .iter()
+                .map(|authority| proof_pubkey(*authority))
+                .collect();
+            let accounts = self
+                .rpc_client
+                .get_multiple_accounts(&addresses)
+                .await
+                .map_err(|err| format!("Failed to get proof accounts: {}", err))?;
+            for (authority, account) in chunk.iter().zip(accounts) {
+                let proof = account
+                    .as_ref()
+                    .and_then(|account| Proof::try_from_bytes(&account.data).ok());
+                rows.push(match proof {
+                    Some(proof) => {
+                        let age = clock
+                            .unix_timestamp
+                            .saturating_sub(proof.last_hash_at)
+                            .max(0);
+                        FleetRow {
+                            authority: authority.to_string(),
+                            stake: amount_u64_to_f64(proof.balance),
+                            multiplier: calculate_multiplier(proof.balance, config.top_balance),
+                            last_hash_age: Some(age),
+                            total_hashes: proof.total_hashes,
+                            total_rewards: amount_u64_to_f64(proof.total_rewards),
+                            down: (age as u64).gt(&down_after.as_secs()),
+                        }
+                    }
+                    None => FleetRow {
+                        authority: authority.to_string(),
+                        stake: 0.0,
+                        multiplier: 1.0,
+                        last_hash_age: None,
+                        total_hashes: 0,
+                        total_rewards: 0.0,
+                        down: true,
+                    },
+                });
+            }
+        }
+        Ok(rows)
+    }
+
+    /// Shows the fleet as a table that refreshes every interval. Left and right change the sort
+    /// column, r reverses it, up, down, page up and page down scroll, and q quits.
+    async fn fleet_table(
+        &self,
+        authorities: &[Pubkey],
+        down_after: Duration,
+        args: &FleetArgs,
+    ) -> io::Result<()> {
+        let mut terminal = take_over_terminal()?;
+        let result = self
+            .run_fleet_table(&mut terminal, authorities, down_after, args)
+            .await;
+        restore_terminal();
+        result
+    }
+
+    /// Draws the fleet table and handles keys until quit.
+    async fn run_fleet_table(
+        &self,
+        terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+        authorities: &[Pubkey],
+        down_after: Duration,
+        args: &FleetArgs,
+    ) -> io::Result<()> {
+        let columns = FleetColumn::value_variants();
+        let mut sort = args.sort.unwrap_or(FleetColumn::Stake);
+        let mut descending = default_descending(sort);
+        let interval = Duration::from_secs(args.interval.max(1));
+
+        let mut rows = vec![];
+        let mut status = "Fetching proofs...".to_string();
+        let mut last_refresh: Option<Instant> = None;
+        let mut offset = 0;
+        let mut page = 0;
+        loop {
+            if last_refresh.map_or(true, |time| time.elapsed() >= interval) {
+                terminal.draw(|frame| {
+                    page = draw_fleet(frame, &rows, sort, descending, &mut offset, &status)
+                })?;
+                match self.fetch_fleet(authorities, down_after).await {
+                    Ok(fetched) => {
+                        rows = fetched;
+                        status = String::new();
+                    }
+                    Err(err) => status = err,
+                }
+                last_refresh = Some(Instant::now());
+            }
+            sort_rows(&mut rows, sort, descending);
+            let footer = if status.is_empty() {
+                format!(
+                    "Updated {}s ago",
+                    last_refresh.map_or(0, |time| time.elapsed().as_secs())
+                )
+            } else {
+                status.clone()
+            };
+            terminal.draw(|frame| {
+                page = draw_fleet(frame, &rows, sort, descending, &mut offset, &footer)
+            })?;
+
+            if !event::poll(INPUT_POLL_INTERVAL)? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind.ne(&KeyEventKind::Press) {
+                continue;
+            }
+            let index = columns
+                .iter()
+                .position(|column| column.eq(&sort))
+                .unwrap_or(0);
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(())
+                }
+                KeyCode::Right | KeyCode::Tab => {
+                    sort = columns[(index + 1) % columns.len()];
+                    descending = default_descending(sort);
+                }
+                KeyCode::Left | KeyCode::BackTab => {
+                    sort = columns[(index + columns.len() - 1) % columns.len()];
+                    descending = default_descending(sort);
+                }
+                KeyCode::Char('r') => descending = !descending,
+                // Scrolling past the last row is clamped when drawing
+                KeyCode::Up => offset = offset.saturating_sub(1),
+                KeyCode::Down => offset += 1,
+                KeyCode::PageUp => offset = offset.saturating_sub(page),
+                KeyCode::PageDown => offset += page,
+                KeyCode::Home => offset = 0,
+                KeyCode::End => offset = rows.len(),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Reads one authority per line, skipping blank lines and comments.
+fn read_authorities(path: &str) -> Result<Vec<Pubkey>, String> {
+    let text =
+        std::fs::read_to_string(path).map_err(|err| format!("Failed to read {}: {}", path, err))?;
+    let authorities: Vec<Pubkey> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            Pubkey::from_str(line).map_err(|_| format!("Invalid authority in {}: {}", path, line))
+        })
+        .collect::<Result<_, _>>()?;
+    if authorities.is_empty() {
+        return Err(format!("No authorities found in {}", path));
+    }
+    Ok(authorities)
+}
+
+/// Returns true if the column sorts largest first by default.
+fn default_descending(column: FleetColumn) -> bool {
+    !matches!(column, FleetColumn::Authority)
+}
+
+fn sort_rows(rows: &mut [FleetRow], column: FleetColumn, descending: bool) {
+    rows.sort_by(|a, b| {
+        let ordering = match column {
+            FleetColumn::Authority => a.authority.cmp(&b.authority),
+            FleetColumn::Stake => a.stake.total_cmp(&b.stake),
+            FleetColumn::Multiplier => a.multiplier.total_cmp(&b.multiplier),
+            // Missing proofs sort as the oldest
+            FleetColumn::LastHash => a
+                .last_hash_age
+                .unwrap_or(i64::MAX)
+                .cmp(&b.last_hash_age.unwrap_or(i64::MAX)),
+            FleetColumn::Hashes => a.total_hashes.cmp(&b.total_hashes),
+            FleetColumn::Rewards => a.total_rewards.total_cmp(&b.total_rewards),
+        };
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+}
+
+/// Draws the rows from `offset` on, clamping it so the last page stays full, and returns how
+/// many rows fit on screen.
+fn draw_fleet(
+    frame: &mut Frame,
+    rows: &[FleetRow],
+    sort: FleetColumn,
+    descending: bool,
+    offset: &mut usize,
+    status: &str,
+) -> usize {
+    let [table_area, footer_area] =
+        Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.size());
+
+    // Leave room for the borders and the header
+    let page = (table_area.height as usize).saturating_sub(3);
+    *offset = (*offset).min(rows.len().saturating_sub(page));
+
+    let header = FleetColumn::value_variants().iter().map(|column| {
+        let name = column.to_possible_value().unwrap().get_name().to_string();
+        if column.eq(&sort) {
+            format!("{} {}", name, if descending { "▼" } else { "▲" })
+        } else {
+            name
+        }
+    });
+    let table_rows = rows.iter().skip(*offset).take(page).map(|row| {
+        let style = match (row.down, row.last_hash_age) {
+            (_, None) => Style::default().fg(Color::DarkGray),
+            (true, _) => Style::default().fg(Color::Red),
+            (false, _) => Style::default(),
+        };
+        Row::new(vec![
+            row.authority.clone(),
+            format!("{:.4}", row.stake),
+            format!("{:.2}x", row.multiplier),
+            row.last_hash_age.map_or("no proof".to_string(), format_age),
+            row.total_hashes.to_string(),
+            format!("{:.4}", row.total_rewards),
+        ])
+        .style(style)
+    });
+    let down = rows.iter().filter(|row| row.down).count();
+    let title = format!(
+        " Fleet: {} authorities, {} down, {:.4} ORE staked ",
+        rows.len(),
+        down,
+        rows.iter().map(|row| row.stake).sum::<f64>()
+    );
+    let table = Table::new(
+        table_rows,
+        [
+            Constraint::Length(44),
+            Constraint::Length(14),
+            Constraint::Length(12),
+            Constraint::Length(12),
+            Constraint::Length(14),
+            Constraint::Length(14),
+        ],
+    )
+    .header(Row::new(header).style(Style::default().add_modifier(Modifier::BOLD)))
+    .block(Block::default().borders(Borders::ALL).title(title));
+    frame.render_widget(table, table_area);
+
+    let position = if rows.len() > page {
+        format!(
+            "  rows {}-{} of {}",
+            *offset + 1,
+            (*offset + page).min(rows.len()),
+            rows.len()
+        )
+    } else {
+        String::new()
+    };
+    let footer = format!(
+        "{}{}  ←/→ sort  r reverse  ↑/↓ scroll  q quit",
+        status, position
+    );
+    frame.render_widget(Paragraph::new(Line::from(footer)), footer_area);
+    page
+}
+
+/// The file formats the fleet can be exported to.
+#[derive(Clone, Copy)]
+enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// Picks the export format from the file extension.
+fn export_format(path: &str) -> Result<ExportFormat, String> {
+    let extension = std::path::Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase);
+    match extension.as_deref() {
+        Some("csv") => Ok(ExportFormat::Csv),
+        Some("json") => Ok(ExportFormat::Json),
+        _ => Err(format!(
+            "Cannot export to {}: use a .csv or .json file",
+            path
+        )),
+    }
+}
+
+/// Writes the rows to a CSV or JSON file.
+fn export(path: &str, format: ExportFormat, rows: &[FleetRow]) -> Result<(), String> {
+    let write_error = |err: &dyn std::fmt::Display| format!("Failed to write {}: {}", path, err);
+    match format {
+        ExportFormat::Json => {
+            let json = serde_json::to_string_pretty(rows).unwrap();
+            std::fs::write(path, json).map_err(|err| write_error(&err))
+        }
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_path(path).map_err(|err| write_error(&err))?;
+            for row in rows {
+                writer.serialize(row).map_err(|err| write_error(&err))?;
+            }
+            writer.flush().map_err(|err| write_error(&err))
+        }
+    }
+}
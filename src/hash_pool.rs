@@ -0,0 +1,193 @@
+use std::{
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    time::Instant,
+};
+
+use drillx::{equix, Hash};
+use tokio::sync::oneshot;
+
+use crate::{cores::HashThread, difficulty::MAX_TRACKED_DIFFICULTY, telemetry::Telemetry};
+
+/// The number of nonces a worker hashes between adding its counts to the telemetry.
+const TELEMETRY_BATCH_NONCES: u64 = 16;
+
+/// A unit of hashing work for a single worker.
+pub struct Work {
+    pub challenge: [u8; 32],
+    pub nonces: Range<u64>,
+    pub cutoff: Instant,
+    pub min_difficulty: u32,
+}
+
+/// The best hash a worker found for its work item, along with hashing stats. Hashes found at
+/// each difficulty are counted in the telemetry of the round.
+#[derive(Default)]
+pub struct WorkResult {
+    pub core: usize,
+    pub nonce: u64,
+    pub difficulty: u32,
+    pub hash: Hash,
+    pub nonces_checked: u64,
+    pub hashes_checked: u64,
+}
+
+struct Job {
+    work: Work,
+    stop: Option<Arc<AtomicBool>>,
+    /// The stats of the round, and the index of this worker in them.
+    telemetry: Arc<Telemetry>,
+    worker: usize,
+    result: oneshot::Sender<WorkResult>,
+}
+
+/// A persistent pool of hashing threads, one per configured hash thread.
+/// Workers live across rounds so their equix solver memory is only allocated once.
+pub struct HashPool {
+    workers: Vec<(HashThread, mpsc::Sender<Job>)>,
+}
+
+impl HashPool {
+    pub fn new(threads: &[HashThread]) -> Self {
+        let workers = threads.iter().copied().map(Self::spawn_worker).collect();
+        Self { workers }
+    }
+
+    fn spawn_worker(thread: HashThread) -> (HashThread, mpsc::Sender<Job>) {
+        let (tx, rx) = mpsc::channel::<Job>();
+        std::thread::spawn(move || {
+            thread.pin();
+            let mut memory = equix::SolverMemory::new();
+            while let Ok(job) = rx.recv() {
+                let result = Self::run(&mut memory, thread, &job);
+                let _ = job.result.send(result);
+            }
+        });
+        (thread, tx)
+    }
+
+    /// Changes the pool to the given threads, keeping the workers already pinned the same way to
+    /// the same cores and spawning the rest. Dropped workers exit once their current job is done.
+    pub fn resize(&mut self, threads: &[HashThread]) {
+        let mut running = std::mem::take(&mut self.workers);
+        self.workers = threads
+            .iter()
+            .copied()
+            .map(|thread| {
+                match running.iter().position(|(worker, _)| {
+                    worker.core.id.eq(&thread.core.id) && worker.pin.eq(&thread.pin)
+                }) {
+                    Some(i) => running.swap_remove(i),
+                    None => Self::spawn_worker(thread),
+                }
+            })
+            .collect();
+    }
+
+    /// Creates the stats for a round hashed by this pool.
+    pub fn telemetry(&self) -> Arc<Telemetry> {
+        Arc::new(Telemetry::new(
+            self.workers.iter().map(|(thread, _)| thread.core.id),
+        ))
+    }
+
+    /// The number of hashing threads in the pool.
+    pub fn len(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Splits the nonce space across all workers and hashes the challenge until the cutoff, or
+    /// until `stop` is set, returning the best result of each worker. Progress is recorded in
+    /// `telemetry`, which is marked finished once every worker is done.
+    pub async fn hash(
+        &self,
+        challenge: [u8; 32],
+        cutoff: Instant,
+        min_difficulty: u32,
+        stop: Option<Arc<AtomicBool>>,
+        telemetry: Arc<Telemetry>,
+    ) -> Vec<WorkResult> {
+        let step = u64::MAX.saturating_div(self.len().max(1) as u64);
+        let receivers: Vec<_> = self
+            .workers
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (_, worker))| {
+                let start = step.saturating_mul(i as u64);
+                let (tx, rx) = oneshot::channel();
+                let job = Job {
+                    work: Work {
+                        challenge,
+                        nonces: start..start.saturating_add(step),
+                        cutoff,
+                        min_difficulty,
+                    },
+                    stop: stop.clone(),
+                    telemetry: Arc::clone(&telemetry),
+                    worker: i,
+                    result: tx,
+                };
+                worker.send(job).ok().map(|_| rx)
+            })
+            .collect();
+
+        let results = futures::future::join_all(receivers)
+            .await
+            .into_iter()
+            .filter_map(Result::ok)
+            .collect();
+        telemetry.finish();
+        results
+    }
+
+    fn run(memory: &mut equix::SolverMemory, thread: HashThread, job: &Job) -> WorkResult {
+        let work = &job.work;
+        let mut best = WorkResult {
+            core: thread.core.id,
+            nonce: work.nonces.start,
+            ..Default::default()
+        };
+        // Counted locally and added to the telemetry in batches
+        let mut difficulties = [0u64; MAX_TRACKED_DIFFICULTY + 1];
+        let mut nonce = work.nonces.start;
+        while nonce < work.nonces.end {
+            let hxs = drillx::hashes_with_memory(memory, &work.challenge, &nonce.to_le_bytes());
+            for hx in hxs {
+                let difficulty = hx.difficulty();
+                best.hashes_checked += 1;
+                difficulties[(difficulty as usize).min(MAX_TRACKED_DIFFICULTY)] += 1;
+                if difficulty > best.difficulty {
+                    best.nonce = nonce;
+                    best.difficulty = difficulty;
+                    best.hash = hx;
+                    job.telemetry.record_best(job.worker, difficulty);
+                }
+            }
+            nonce += 1;
+            if (nonce - work.nonces.start) % TELEMETRY_BATCH_NONCES == 0 {
+                job.telemetry.record(job.worker, &difficulties);
+                difficulties = [0; MAX_TRACKED_DIFFICULTY + 1];
+            }
+
+            // Other workers may have reached the minimum difficulty already
+            if Instant::now() >= work.cutoff
+                && job.telemetry.best_difficulty() >= work.min_difficulty
+            {
+                break;
+            }
+            if job
+                .stop
+                .as_ref()
+                .map_or(false, |stop| stop.load(Ordering::Relaxed))
+            {
+                break;
+            }
+        }
+        job.telemetry.record(job.worker, &difficulties);
+        best.nonces_checked = nonce - work.nonces.start;
+        best
+    }
+}
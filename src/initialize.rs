@@ -16,7 +16,7 @@ impl Miner {
         let tx = Transaction::new_signed_with_payer(
             &[ix],
             Some(&self.signer().pubkey()),
-            &[&self.signer()],
+            &[self.signer()],
             blockhash,
         );
         let res = self.rpc_client.send_and_confirm_transaction(&tx).await;
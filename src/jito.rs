@@ -0,0 +1,162 @@
+use std::{
+    sync::atomic::{AtomicU64, AtomicU8, Ordering},
+    time::{Duration, Instant},
+};
+
+use clap::ValueEnum;
+use colored::*;
+use futures::future::join_all;
+
+use crate::{args::JitoRegion, utils::Tip};
+
+/// The default Jito tip stream, which publishes landed tip percentiles.
+pub const DEFAULT_TIP_STREAM_URL: &str = "wss://bundles.jito.wtf/api/v1/bundles/tip_stream";
+
+/// How long to wait for a block engine to respond when measuring latency.
+const LATENCY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The percentiles published by the tip stream.
+pub const TIP_PERCENTILES: [u8; 5] = [25, 50, 75, 95, 99];
+
+/// Checks that the tip stream publishes the given landed tip percentile.
+pub fn check_tip_percentile(percentile: u8) -> Result<u8, String> {
+    if !TIP_PERCENTILES.contains(&percentile) {
+        return Err(format!(
+            "Tip percentile must be one of {:?}, got {}",
+            TIP_PERCENTILES, percentile
+        ));
+    }
+    Ok(percentile)
+}
+
+/// Parses a landed tip percentile from the command line.
+pub fn parse_tip_percentile(arg: &str) -> Result<u8, String> {
+    check_tip_percentile(
+        arg.parse()
+            .map_err(|_| format!("Invalid percentile: {}", arg))?,
+    )
+}
+
+const REGIONS: [JitoRegion; 5] = [
+    JitoRegion::Mainnet,
+    JitoRegion::Ny,
+    JitoRegion::Amsterdam,
+    JitoRegion::Frankfurt,
+    JitoRegion::Tokyo,
+];
+
+impl JitoRegion {
+    /// Returns the block engine host of the region, or none for automatic selection.
+    pub fn host(&self) -> Option<&'static str> {
+        match self {
+            JitoRegion::Auto => None,
+            JitoRegion::Mainnet => Some("mainnet.block-engine.jito.wtf"),
+            JitoRegion::Ny => Some("ny.mainnet.block-engine.jito.wtf"),
+            JitoRegion::Amsterdam => Some("amsterdam.mainnet.block-engine.jito.wtf"),
+            JitoRegion::Frankfurt => Some("frankfurt.mainnet.block-engine.jito.wtf"),
+            JitoRegion::Tokyo => Some("tokyo.mainnet.block-engine.jito.wtf"),
+        }
+    }
+
+    /// Returns the transactions endpoint of the region's block engine.
+    pub fn block_engine_url(&self) -> Option<String> {
+        self.host()
+            .map(|host| format!("https://{}/api/v1/transactions", host))
+    }
+}
+
+/// Resolves the block engine to send tipped transactions to. An explicit url wins over a
+/// region, and the region with the lowest latency is picked if neither is set.
+pub async fn resolve_block_engine_url(url: Option<String>, region: Option<JitoRegion>) -> String {
+    if let Some(url) = url {
+        return url;
+    }
+    if let Some(url) = region.and_then(|region| region.block_engine_url()) {
+        return url;
+    }
+    match fastest_region().await {
+        Some((region, latency)) => {
+            println!(
+                "Using Jito block engine in {} ({} ms)",
+                region.to_possible_value().unwrap().get_name(),
+                latency.as_millis()
+            );
+            region.block_engine_url().unwrap()
+        }
+        None => {
+            println!(
+                "{} Could not reach any Jito block engine. Falling back to mainnet.",
+                "WARNING".bold().yellow()
+            );
+            JitoRegion::Mainnet.block_engine_url().unwrap()
+        }
+    }
+}
+
+/// Measures the round trip time to each region's block engine and returns the fastest.
+async fn fastest_region() -> Option<(JitoRegion, Duration)> {
+    let client = reqwest::Client::builder()
+        .timeout(LATENCY_TIMEOUT)
+        .build()
+        .ok()?;
+    let latencies = join_all(REGIONS.iter().map(|region| {
+        let client = client.clone();
+        async move {
+            let url = format!("https://{}/api/v1/bundles", region.host()?);
+            let timer = Instant::now();
+            // Any response counts, since only the round trip time matters.
+            client.get(url).send().await.ok()?;
+            Some((*region, timer.elapsed()))
+        }
+    }))
+    .await;
+    latencies
+        .into_iter()
+        .flatten()
+        .min_by_key(|(_, latency)| *latency)
+}
+
+/// How tips are picked from the tip stream. They can change while mining through the control
+/// socket, but the percentile is always one the tip stream publishes.
+pub struct TipSettings {
+    percentile: AtomicU8,
+    max_tip: AtomicU64,
+}
+
+impl TipSettings {
+    /// Fails unless the tip stream publishes the percentile.
+    pub fn new(percentile: u8, max_tip: u64) -> Result<Self, String> {
+        Ok(Self {
+            percentile: AtomicU8::new(check_tip_percentile(percentile)?),
+            max_tip: AtomicU64::new(max_tip),
+        })
+    }
+
+    /// The landed tip percentile to pay.
+    pub fn percentile(&self) -> u8 {
+        self.percentile.load(Ordering::Relaxed)
+    }
+
+    /// Changes the landed tip percentile to pay. Fails unless the tip stream publishes it.
+    pub fn set_percentile(&self, percentile: u8) -> Result<(), String> {
+        self.percentile
+            .store(check_tip_percentile(percentile)?, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// The most to tip, in lamports.
+    pub fn max_tip(&self) -> u64 {
+        self.max_tip.load(Ordering::Relaxed)
+    }
+
+    /// Changes the most to tip, in lamports.
+    pub fn set_max_tip(&self, max_tip: u64) {
+        self.max_tip.store(max_tip, Ordering::Relaxed);
+    }
+
+    /// Returns the tip to pay for the given landed tips, in lamports.
+    pub fn tip(&self, tips: &Tip) -> u64 {
+        let tip = tips.percentile(self.percentile()).unwrap_or_default();
+        ((tip * 10_f64.powf(9.0)) as u64).min(self.max_tip())
+    }
+}
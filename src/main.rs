@@ -1,31 +1,75 @@
 mod args;
+mod auto_claim;
 mod balance;
 mod benchmark;
+mod bus;
+mod claim;
+mod config_file;
+mod control;
+mod control_socket;
+mod cores;
 mod cu_limits;
+mod dashboard;
+mod difficulty;
 mod error;
-
+mod estimate;
+mod fleet;
+mod hash_pool;
+mod jito;
 mod mine;
+mod nonce;
+mod offline;
 mod proof;
 mod rewards;
+mod rpc_pool;
 mod send_and_confirm;
+mod session;
+mod signer;
+mod signer_server;
+mod stake;
+mod submit;
+mod telemetry;
+mod transfer;
 mod utils;
+mod watch;
 
 use futures::StreamExt;
-use std::{ sync::Arc, sync::RwLock };
+use std::{ cell::{ Cell, OnceCell }, str::FromStr, sync::Arc, sync::RwLock };
 use tokio_tungstenite::connect_async;
 use tokio_tungstenite::tungstenite::protocol::Message;
 
 use args::*;
 use clap::{ command, Parser, Subcommand };
 use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_sdk::{ commitment_config::CommitmentConfig, signature::{ read_keypair_file, Keypair } };
+use colored::*;
+use solana_sdk::{ commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signer };
+use config_file::{ expand_home, ConfigFile, Settings };
+use jito::{ resolve_block_engine_url, TipSettings, DEFAULT_TIP_STREAM_URL };
+use rpc_pool::RpcPool;
+use signer::{ resolve_signer, RemoteSigner };
 use utils::Tip;
 
 struct Miner {
     pub keypair_filepath: Option<String>,
+    pub fee_payer_filepath: Option<String>,
     pub rpc_client: Arc<RpcClient>,
+    pub rpc_pool: Arc<RpcPool>,
     pub jito_client: Arc<RpcClient>,
     pub tip: Arc<std::sync::RwLock<u64>>,
+    /// How tips are picked from the tip stream, if Jito is enabled.
+    pub tip_settings: Option<Arc<TipSettings>>,
+    pub dynamic_fee: bool,
+    pub signer_url: Option<String>,
+    pub signer_token: Option<String>,
+    pub nonce: Option<Pubkey>,
+    pub nonce_authority_filepath: Option<String>,
+    pub profile: Option<String>,
+    pub settings: Settings,
+    signer: OnceCell<Box<dyn Signer>>,
+    fee_payer: OnceCell<Option<Box<dyn Signer>>>,
+    nonce_authority: OnceCell<Option<Box<dyn Signer>>>,
+    /// The priority fee paid by the last transaction sent, in microlamports.
+    last_priority_fee: Cell<u64>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -34,13 +78,37 @@ enum Commands {
 
     #[command(about = "Benchmark your hashpower")] Benchmark(BenchmarkArgs),
 
+    #[command(about = "Claim your mining rewards")] Claim(ClaimArgs),
+
+    #[command(about = "Manage the ore config file")] ConfigFile(ConfigFileArgs),
+
+    #[command(about = "Adjust a running miner through its control socket")] Ctl(CtlArgs),
+
+    #[command(about = "Estimate expected mining rewards and profit")] Estimate(EstimateArgs),
+
+    #[command(about = "Monitor the proofs of many authorities at once")] Fleet(FleetArgs),
+
+    #[command(about = "Manage keypair files")] Keypair(KeypairArgs),
+
     #[command(about = "Start mining")] Mine(MineArgs),
 
+    #[command(about = "Manage durable nonce accounts")] Nonce(NonceArgs),
+
     #[command(about = "Fetch a proof account by address")] Proof(ProofArgs),
 
     #[command(about = "Fetch the current reward rate for each difficulty level")] Rewards(
         RewardsArgs,
     ),
+
+    #[command(about = "Serve signatures for remote miners, restricted to mining instructions")] SignerServer(
+        SignerServerArgs,
+    ),
+
+    #[command(about = "Stake ORE to earn a multiplier on your mining rewards")] Stake(StakeArgs),
+
+    #[command(about = "Broadcast a transaction signed with --sign-only")] Submit(SubmitArgs),
+
+    #[command(about = "Send ORE to another wallet")] Transfer(TransferArgs),
 }
 
 #[derive(Parser, Debug)]
@@ -49,7 +117,8 @@ struct Args {
     #[arg(
         long,
         value_name = "NETWORK_URL",
-        help = "Network address of your RPC provider",
+        help = "Network address of your RPC provider. Pass a comma-separated list to fail over between providers and broadcast transactions to all of them.",
+        env = "ORE_RPC",
         global = true
     )]
     rpc: Option<String>,
@@ -59,14 +128,26 @@ struct Args {
         short = 'C',
         long = "config",
         id = "PATH",
-        help = "Filepath to config file."
+        help = "Filepath to config file.",
+        env = "ORE_CONFIG"
     )]
     config_file: Option<String>,
 
+    #[arg(
+        long,
+        value_name = "PROFILE",
+        help = "Profile from the ore config file to load settings from.",
+        env = "ORE_PROFILE",
+        global = true
+    )]
+    profile: Option<String>,
+
     #[arg(
         long,
         value_name = "KEYPAIR_FILEPATH",
-        help = "Filepath to signer keypair.",
+        help = "Filepath or URI of the signer keypair, e.g. usb://ledger, prompt:// or stdin. The environment variable may also hold the keypair as base58 or JSON bytes.",
+        env = "ORE_KEYPAIR",
+        hide_env_values = true,
         global = true
     )]
     keypair: Option<String>,
@@ -74,22 +155,90 @@ struct Args {
     #[arg(
         long,
         value_name = "FEE_PAYER_FILEPATH",
-        help = "Filepath to transaction fee payer keypair.",
+        help = "Filepath or URI of the transaction fee payer keypair. The environment variable may also hold the keypair as base58 or JSON bytes.",
+        env = "ORE_FEE_PAYER",
+        hide_env_values = true,
         global = true
     )]
     fee_payer: Option<String>,
 
-    #[arg(long, help = "Enable dynamic priority fees", global = true)]
+    #[arg(
+        long,
+        value_name = "URL",
+        help = "URL of an ore signer-server to sign transactions with instead of a local keypair.",
+        env = "ORE_SIGNER_URL",
+        global = true
+    )]
+    signer_url: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "TOKEN",
+        help = "Bearer token shared between miners and the signer-server.",
+        env = "ORE_SIGNER_TOKEN",
+        hide_env_values = true,
+        global = true
+    )]
+    signer_token: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "NONCE_ACCOUNT",
+        help = "Durable nonce account to sign transactions with instead of a recent blockhash, so signatures do not expire.",
+        env = "ORE_NONCE",
+        global = true
+    )]
+    nonce: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "KEYPAIR_FILEPATH",
+        help = "Filepath or URI of the nonce authority keypair. Defaults to the signer.",
+        env = "ORE_NONCE_AUTHORITY",
+        hide_env_values = true,
+        global = true
+    )]
+    nonce_authority: Option<String>,
+
+    #[arg(long, help = "Enable dynamic priority fees", env = "ORE_DYNAMIC_FEE", global = true)]
     dynamic_fee: bool,
 
     #[arg(
         long,
         value_name = "JITO",
         help = "Add jito tip to the miner. Defaults to false.",
+        env = "ORE_JITO",
         global = true
     )]
     jito: bool,
 
+    #[arg(
+        long,
+        value_name = "URL",
+        help = "Jito block engine transactions endpoint. Overrides --jito-region.",
+        env = "ORE_JITO_URL",
+        global = true
+    )]
+    jito_url: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "REGION",
+        help = "Jito block engine region. Defaults to the region with the lowest latency.",
+        env = "ORE_JITO_REGION",
+        global = true
+    )]
+    jito_region: Option<JitoRegion>,
+
+    #[arg(
+        long,
+        value_name = "URL",
+        help = "Jito tip stream websocket endpoint.",
+        env = "ORE_JITO_TIP_STREAM",
+        global = true
+    )]
+    jito_tip_stream: Option<String>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -110,20 +259,62 @@ async fn main() {
         solana_cli_config::Config::default()
     };
 
+    // Load settings from the ore config file
+    let settings = ConfigFile::load().settings(args.profile.as_deref());
+
     // Initialize miner.
-    let cluster = args.rpc.unwrap_or(cli_config.json_rpc_url);
-    let default_keypair = args.keypair.unwrap_or(cli_config.keypair_path.clone());
-    let rpc_client = RpcClient::new_with_commitment(cluster, CommitmentConfig::confirmed());
-    let jito_client = RpcClient::new(
-        "https://mainnet.block-engine.jito.wtf/api/v1/transactions".to_string()
+    let cluster = args.rpc.or(settings.rpc.clone()).unwrap_or(cli_config.json_rpc_url);
+    let default_keypair = args.keypair
+        .or(settings.keypair.clone().map(expand_home))
+        .unwrap_or(cli_config.keypair_path.clone());
+    let fee_payer = args.fee_payer.or(settings.fee_payer.clone().map(expand_home));
+    let signer_url = args.signer_url.or(settings.signer_url.clone());
+    let nonce = args.nonce.or(settings.nonce.clone()).map(|nonce| {
+        Pubkey::from_str(&nonce).unwrap_or_else(|_| {
+            exit_with_error(&format!("Invalid nonce account: {}", nonce))
+        })
+    });
+    let nonce_authority = args.nonce_authority.or(
+        settings.nonce_authority.clone().map(expand_home)
     );
+    let urls: Vec<String> = cluster
+        .split(',')
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .map(String::from)
+        .collect();
+    if urls.is_empty() {
+        exit_with_error("No RPC provided");
+    }
+    let rpc_pool = RpcPool::new(urls, CommitmentConfig::confirmed());
+    rpc_pool.spawn_health_checks();
+    let rpc_client = rpc_pool.client(CommitmentConfig::confirmed());
+    let jito = args.jito || settings.jito.unwrap_or(false);
+    let jito_url = args.jito_url.or(settings.jito_url.clone());
+    let jito_region = args.jito_region.or(settings.jito_region);
+    let jito_client = RpcClient::new(if jito {
+        resolve_block_engine_url(jito_url, jito_region).await
+    } else {
+        jito_url.unwrap_or(JitoRegion::Mainnet.block_engine_url().unwrap())
+    });
 
     let tip = Arc::new(RwLock::new(0_u64));
     let tip_clone = Arc::clone(&tip);
+    let tip_settings = jito.then(|| {
+        let tip_settings = TipSettings::new(
+            settings.tip_percentile.unwrap_or(25),
+            settings.max_tip.unwrap_or(u64::MAX)
+        ).unwrap_or_else(|err| exit_with_error(&err));
+        Arc::new(tip_settings)
+    });
 
-    if args.jito {
-        let url = "ws://bundles-api-rest.jito.wtf/api/v1/bundles/tip_stream";
-        let (ws_stream, _) = connect_async(url).await.unwrap();
+    if let Some(tip_settings) = tip_settings.clone() {
+        let url = args.jito_tip_stream
+            .or(settings.jito_tip_stream.clone())
+            .unwrap_or(DEFAULT_TIP_STREAM_URL.to_string());
+        let (ws_stream, _) = connect_async(&url).await.unwrap_or_else(|err| {
+            exit_with_error(&format!("Failed to connect to Jito tip stream at {}: {}", url, err))
+        });
         let (_, mut read) = ws_stream.split();
 
         tokio::spawn(async move {
@@ -132,7 +323,7 @@ async fn main() {
                     if let Ok(tips) = serde_json::from_str::<Vec<Tip>>(&text) {
                         for item in tips {
                             let mut tip = tip_clone.write().unwrap();
-                            *tip = (item.landed_tips_25th_percentile * (10_f64).powf(9.0)) as u64;
+                            *tip = tip_settings.tip(&item);
                         }
                     }
                 }
@@ -140,8 +331,21 @@ async fn main() {
         });
     }
 
-    let miner = Arc::new(
-        Miner::new(Arc::new(rpc_client), Some(default_keypair), Arc::new(jito_client), tip)
+    let miner = Miner::new(
+        Arc::new(rpc_client),
+        rpc_pool,
+        Some(default_keypair),
+        fee_payer,
+        signer_url,
+        args.signer_token,
+        nonce,
+        nonce_authority,
+        Arc::new(jito_client),
+        tip,
+        tip_settings,
+        args.dynamic_fee,
+        args.profile,
+        settings
     );
 
     // Execute user command.
@@ -152,13 +356,46 @@ async fn main() {
         Commands::Benchmark(args) => {
             miner.benchmark(args).await;
         }
+        Commands::Claim(args) => {
+            miner.claim(args).await;
+        }
+        Commands::ConfigFile(args) => {
+            miner.config_file(args).await;
+        }
+        Commands::Ctl(args) => {
+            miner.ctl(args).await;
+        }
+        Commands::Estimate(args) => {
+            miner.estimate(args).await;
+        }
+        Commands::Fleet(args) => {
+            miner.fleet(args).await;
+        }
+        Commands::Keypair(args) => {
+            miner.keypair(args).await;
+        }
         Commands::Mine(args) => { miner.mine(args).await }
+        Commands::Nonce(args) => {
+            miner.nonce(args).await;
+        }
         Commands::Proof(args) => {
             miner.proof(args).await;
         }
         Commands::Rewards(_) => {
             miner.rewards().await;
         }
+        Commands::SignerServer(args) => {
+            miner.signer_server(args).await;
+        }
+        Commands::Stake(args) => {
+            miner.stake(args).await;
+        }
+        Commands::Submit(args) => {
+            miner.submit_signed(args).await;
+        }
+        Commands::Transfer(args) => {
+            miner.transfer(args).await;
+        }
         #[cfg(feature = "admin")]
         Commands::Initialize(_) => {
             miner.initialize().await;
@@ -167,27 +404,92 @@ async fn main() {
 }
 
 impl Miner {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         rpc_client: Arc<RpcClient>,
+        rpc_pool: Arc<RpcPool>,
         keypair_filepath: Option<String>,
+        fee_payer_filepath: Option<String>,
+        signer_url: Option<String>,
+        signer_token: Option<String>,
+        nonce: Option<Pubkey>,
+        nonce_authority_filepath: Option<String>,
         jito_client: Arc<RpcClient>,
-        tip: Arc<std::sync::RwLock<u64>>
+        tip: Arc<std::sync::RwLock<u64>>,
+        tip_settings: Option<Arc<TipSettings>>,
+        dynamic_fee: bool,
+        profile: Option<String>,
+        settings: Settings
     ) -> Self {
         Self {
             rpc_client,
+            rpc_pool,
             keypair_filepath,
+            fee_payer_filepath,
+            signer_url,
+            signer_token,
+            nonce,
+            nonce_authority_filepath,
             jito_client,
             tip,
+            tip_settings,
+            dynamic_fee,
+            profile,
+            settings,
+            signer: OnceCell::new(),
+            fee_payer: OnceCell::new(),
+            nonce_authority: OnceCell::new(),
+            last_priority_fee: Cell::new(0),
         }
     }
 
-    pub fn signer(&self) -> Keypair {
-        match self.keypair_filepath.clone() {
-            Some(filepath) =>
-                read_keypair_file(filepath.clone()).expect(
-                    format!("No keypair found at {}", filepath).as_str()
-                ),
-            None => panic!("No keypair provided"),
+    /// Returns the signer, resolving it on first use. Exits if it cannot be loaded.
+    pub fn signer(&self) -> &dyn Signer {
+        self.signer
+            .get_or_init(|| {
+                if let Some(url) = self.signer_url.as_deref() {
+                    return RemoteSigner::connect(url, self.signer_token.clone())
+                        .map(|signer| Box::new(signer) as Box<dyn Signer>)
+                        .unwrap_or_else(|err| exit_with_error(&err));
+                }
+                let Some(source) = self.keypair_filepath.as_deref() else {
+                    exit_with_error("No keypair provided");
+                };
+                resolve_signer(source, "keypair").unwrap_or_else(|err| exit_with_error(&err))
+            })
+            .as_ref()
+    }
+
+    /// Returns the fee payer, falling back to the signer if none is configured.
+    pub fn fee_payer(&self) -> &dyn Signer {
+        let fee_payer = self.fee_payer.get_or_init(|| {
+            self.fee_payer_filepath.as_deref().map(|source| {
+                resolve_signer(source, "fee-payer").unwrap_or_else(|err| exit_with_error(&err))
+            })
+        });
+        match fee_payer {
+            Some(fee_payer) => fee_payer.as_ref(),
+            None => self.signer(),
+        }
+    }
+
+    /// Returns the authority of the durable nonce account, falling back to the signer.
+    pub fn nonce_authority(&self) -> &dyn Signer {
+        let nonce_authority = self.nonce_authority.get_or_init(|| {
+            self.nonce_authority_filepath.as_deref().map(|source| {
+                resolve_signer(source, "nonce-authority").unwrap_or_else(|err| {
+                    exit_with_error(&err)
+                })
+            })
+        });
+        match nonce_authority {
+            Some(nonce_authority) => nonce_authority.as_ref(),
+            None => self.signer(),
         }
     }
 }
+
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("{} {}", "ERROR".bold().red(), message);
+    std::process::exit(1);
+}
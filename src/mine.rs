@@ -1,180 +1,476 @@
-use std::{ sync::Arc, time::Instant, usize, str::FromStr };
+use std::{ cell::{ Cell, RefCell }, sync::Arc, time::{ Duration, Instant }, str::FromStr };
 use bytemuck::{ Pod, Zeroable };
-use std::sync::atomic::AtomicU32;
 use colored::*;
-use std::sync::atomic::Ordering;
-use drillx::{ equix::{ self }, Hash, Solution };
-use ore_api::{ consts::{ BUS_ADDRESSES, BUS_COUNT, CONFIG_ADDRESS }, state::Bus };
+use drillx::Solution;
+use ore_api::{
+    consts::{ BUS_ADDRESSES, BUS_COUNT, CONFIG_ADDRESS, EPOCH_DURATION, TOLERANCE },
+    error::OreError,
+    state::{ Bus, Config },
+};
 use ore_utils::AccountDeserialize;
+use futures::future::join_all;
+use indicatif::ProgressBar;
 use rand::Rng;
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
 use solana_program::{ pubkey::Pubkey, instruction::{ Instruction, AccountMeta }, sysvar };
 use solana_rpc_client::spinner;
-use solana_sdk::signer::Signer;
 
 use crate::{
-    args::MineArgs,
+    args::{ BusStrategy, MineArgs },
+    auto_claim::AutoClaim,
+    bus::BusCandidate,
+    config_file::expand_home,
+    control::{ handle_signals, MineControl },
+    control_socket::ControlSocket,
+    cores::{ core_count, hash_threads, limit_cores },
+    dashboard::{ report, Dashboard, Submission },
+    error::SendError,
+    exit_with_error,
+    hash_pool::HashPool,
+    session::Session,
+    telemetry::Telemetry,
     utils::{
         amount_u64_to_string,
+        calculate_multiplier,
         get_clock,
         get_config,
+        get_proof_with_authority,
         get_updated_proof_with_authority,
         proof_pubkey,
     },
     Miner,
 };
 
+/// The mining pool program that submits solutions on behalf of the proof authority.
+pub const POOL_PROGRAM_ID: Pubkey = solana_program::pubkey!(
+    "tPCXnizACpbtbiAn4NQjvbKC9Tp28BBvkjhaSgqDfhH"
+);
+
+/// The pool authority whose proof `ore mine` submits solutions to.
+pub const POOL_AUTHORITY: Pubkey = solana_program::pubkey!(
+    "5nsXYepY5h8LfbkE8aT79oy5w9eDSTJDUMf345JQdWJ9"
+);
+
+/// The instruction discriminator of the pool program's mine instruction.
+pub const MINE_DISCRIMINATOR: [u8; 8] = [170, 66, 140, 123, 15, 20, 224, 194];
+
+/// The number of other buses to try when a submission fails because its bus ran out of rewards.
+const MAX_BUS_RETRIES: usize = 1;
+
+/// How long to wait before retrying after a transient ORE program error.
+const ERROR_BACKOFF: Duration = Duration::from_secs(5);
+
+/// How often the spinner shows the hashing progress.
+const REPORT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The number of difficulty levels in the distribution reported after each round.
+const DIFFICULTY_SUMMARY_LEVELS: usize = 8;
+
+/// How often to check whether hashing was resumed.
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
 impl Miner {
     pub async fn mine(&self, args: MineArgs) {
         let signer = self.signer();
 
         println!("{} {}", "Mining with".bold().green(), signer.pubkey());
 
-        let miner = Pubkey::from_str("5nsXYepY5h8LfbkE8aT79oy5w9eDSTJDUMf345JQdWJ9").unwrap();
+        let miner = POOL_AUTHORITY;
 
         // Check num threads
-        self.check_num_cores(args.cores);
+        let mut cores = args.cores.or(self.settings.cores).unwrap_or(1);
+        let mut thread_args = args.threads.with_config(&self.settings);
+        if args.cores.is_some() && args.threads.core_list.is_none() {
+            // An explicit core count takes precedence over a saved core list
+            thread_args.core_list = None;
+        }
+        let threads = hash_threads(cores, &thread_args);
+        self.check_num_cores(threads.len() as u64);
+
+        let buffer_time = args.buffer_time.or(self.settings.buffer_time).unwrap_or(5);
+        let bus_strategy = args.bus_strategy
+            .or(self.settings.bus_strategy)
+            .unwrap_or(BusStrategy::WeightedRandom);
+        let mut auto_claim = AutoClaim::new(&args, &self.settings, signer.pubkey());
+        if let Some(auto_claim) = &auto_claim {
+            println!("{}", auto_claim.describe());
+        }
 
         // Start mining loop
         let mut last_hash_at = 0;
         let mut last_balance = 0;
 
-        // Fetch proof
-        let config = get_config(&self.rpc_client).await;
+        let mut landed = false;
 
-        let nonce_indices: Vec<u64> = (0..args.cores)
-            .map(|n| u64::MAX.saturating_div(args.cores).saturating_mul(n))
-            .collect();
+        // Spawn hashing threads once and reuse them across rounds
+        let mut pool = HashPool::new(&threads);
+        cores = core_count(&threads);
+        let control = Arc::new(MineControl::new(cores, buffer_time));
 
-        loop {
-            let proof = get_updated_proof_with_authority(&self.rpc_client, miner).await;
+        // Listen for control requests, if enabled
+        let control_socket = args.control_socket
+            .clone()
+            .or(self.settings.control_socket.clone().map(expand_home))
+            .map(|path| ControlSocket::bind(&path).unwrap_or_else(|err| exit_with_error(&err)));
+        if let Some(socket) = &control_socket {
+            println!("Serving control requests on {}", socket.path().display());
+        }
 
-            println!(
-                "\n\nStake: {} ORE\n{}  Multiplier: {:12}x",
-                amount_u64_to_string(proof.balance),
-                if last_hash_at.gt(&0) {
-                    format!(
-                        "  Change: {} ORE\n",
-                        amount_u64_to_string(proof.balance.saturating_sub(last_balance))
-                    )
-                } else {
-                    "".to_string()
-                },
-                calculate_multiplier(proof.balance, config.top_balance)
+        // Take over the terminal with the dashboard, if enabled
+        let tui = args.tui || self.settings.tui.unwrap_or(false);
+        let dashboard = tui.then(|| {
+            let dashboard = Dashboard::start(signer.pubkey(), Arc::clone(&control)).unwrap_or_else(
+                |err| exit_with_error(&format!("Terminal error: {}", err))
             );
+            dashboard.log(format!("Mining with {}", signer.pubkey()));
+            if let Some(auto_claim) = &auto_claim {
+                dashboard.log(auto_claim.describe());
+            }
+            if let Some(socket) = &control_socket {
+                dashboard.log(format!("Serving control requests on {}", socket.path().display()));
+            }
+            dashboard.set_cores(cores);
+            self.rpc_pool.capture_events();
+            dashboard
+        });
+        let dashboard = dashboard.as_ref();
+
+        // Shut down gracefully on SIGINT and SIGTERM, and pause on SIGUSR1 and SIGUSR2
+        tokio::spawn(handle_signals(Arc::clone(&control), dashboard.is_some()));
+        let session = RefCell::new(Session::start());
+
+        // Lines logged while the spinner is showing, printed between rounds
+        let deferred = RefCell::new(vec![]);
+
+        let mining = async {
+            loop {
+                for line in deferred.take() {
+                    println!("{}", line);
+                }
+
+                // Wait while paused
+                if control.is_paused() {
+                    report(dashboard, "Paused");
+                    while control.is_paused() && !control.is_shutting_down() {
+                        tokio::time::sleep(PAUSE_POLL_INTERVAL).await;
+                    }
+                    if !control.is_shutting_down() {
+                        report(dashboard, "Resumed");
+                    }
+                }
+                if control.is_shutting_down() {
+                    break None;
+                }
+
+                // Apply a changed core count
+                if control.cores().ne(&cores) {
+                    let requested = control.cores();
+                    let threads = limit_cores(hash_threads(requested, &thread_args), requested);
+                    pool.resize(&threads);
+                    cores = control.set_cores(core_count(&threads));
+                    report(dashboard, format!("Mining with {} cores", cores));
+                    if let Some(dashboard) = dashboard {
+                        dashboard.set_cores(cores);
+                    }
+                }
+
+                // Fetch proof
+                let config = get_config(&self.rpc_client).await;
+                let proof = if landed {
+                    let proof = get_updated_proof_with_authority(
+                        &self.rpc_client,
+                        miner,
+                        last_hash_at,
+                        &control
+                    ).await;
+                    // Paused or shutting down while waiting, so start over from the top
+                    let Some(proof) = proof else {
+                        landed = false;
+                        continue;
+                    };
+                    proof
+                } else {
+                    get_proof_with_authority(&self.rpc_client, miner).await
+                };
+
+                let multiplier = calculate_multiplier(proof.balance, config.top_balance);
+                session.borrow_mut().record_stake(proof.balance);
+                match dashboard {
+                    Some(dashboard) => dashboard.set_stake(proof.balance, multiplier),
+                    None =>
+                        println!(
+                            "\n\nStake: {} ORE\n{}  Multiplier: {:12}x",
+                            amount_u64_to_string(proof.balance),
+                            if last_hash_at.gt(&0) {
+                                format!(
+                                    "  Change: {} ORE\n",
+                                    amount_u64_to_string(proof.balance.saturating_sub(last_balance))
+                                )
+                            } else {
+                                "".to_string()
+                            },
+                            multiplier
+                        ),
+                }
+
+                last_hash_at = proof.last_hash_at;
+                last_balance = proof.balance;
+
+                // Keep hashing past the cutoff until the target difficulty is reached, if set
+                let min_difficulty = (config.min_difficulty as u32).max(
+                    control.target_difficulty().unwrap_or(0)
+                );
+
+                let (solution, difficulty) = Self::find_hash_par(
+                    &pool,
+                    proof.challenge,
+                    self.get_cutoff(proof.last_hash_at, control.buffer_time()).await,
+                    min_difficulty,
+                    &control,
+                    &session,
+                    dashboard
+                ).await;
+
+                // Drop the round if hashing was stopped early, since the solution cannot land
+                // before the cutoff, and mine a fresh challenge once resumed
+                if control.is_shutting_down() {
+                    break None;
+                }
+                if control.is_paused() {
+                    landed = false;
+                    continue;
+                }
+
+                // Reset the epoch along with the submission if it is due
+                let reset = self.needs_reset().await;
+                if reset {
+                    report(dashboard, "Resetting epoch...");
+                }
+
+                // Submit transaction, moving to another bus if it fails to land
+                let reward = expected_reward(&config, proof.balance, difficulty);
+                let mut tried_buses = vec![];
+                let mut last_write_slots = None;
+                let result = loop {
+                    let bus = self.find_bus(
+                        bus_strategy,
+                        reward,
+                        &tried_buses,
+                        reset,
+                        &mut last_write_slots
+                    ).await;
+                    tried_buses.push(bus);
+                    let mut ixs = vec![];
+                    if reset {
+                        ixs.push(ore_api::instruction::reset(signer.pubkey()));
+                    }
+                    ixs.push(ore_api::instruction::auth(proof_pubkey(miner)));
+                    ixs.push(
+                        mine(
+                            signer.pubkey(),
+                            miner,
+                            Pubkey::from_str(
+                                "6btvikiSJwq7rArfD9s77g1EBnurMFQ1rxBwUfxY2jU8"
+                            ).unwrap(),
+                            bus,
+                            solution
+                        )
+                    );
+                    let result = self.submit_solution(&ixs, difficulty, dashboard).await;
+                    // Only retry a transaction that failed on chain, so the solution cannot have
+                    // landed, and only if the bus no longer holds enough rewards
+                    match &result {
+                        Err(SendError::Transaction { .. }) if
+                            tried_buses.len() <= MAX_BUS_RETRIES &&
+                            self.is_bus_exhausted(bus, reward).await
+                        => {
+                            report(
+                                dashboard,
+                                "Bus is out of rewards. Retrying on a different bus..."
+                            );
+                        }
+                        _ => {
+                            break result;
+                        }
+                    }
+                };
+                landed = result.is_ok();
+                session.borrow_mut().record_submission(landed);
+
+                // React to ORE program errors instead of blindly retrying
+                match result.err().and_then(|err| err.ore_error()) {
+                    Some(OreError::Spam) | Some(OreError::ClockInvalid) => {
+                        tokio::time::sleep(ERROR_BACKOFF).await;
+                    }
+                    Some(err @ (OreError::AuthFailed | OreError::MaxSupply)) => {
+                        break Some(err);
+                    }
+                    // Stale challenges, difficulties and resets are refreshed next round
+                    _ => {}
+                }
+            }
+        };
+
+        // Claim rewards on a separate schedule, so a slow claim never holds up a submission. The
+        // claims stop once mining ends, letting a claim in flight finish.
+        let claiming = async {
+            if let Some(auto_claim) = auto_claim.as_mut() {
+                self.run_auto_claim(auto_claim, &control, dashboard, &deferred).await;
+            }
+        };
+        let mining = async {
+            let stopping = async {
+                let error = mining.await;
+                control.shut_down();
+                error
+            };
+            let running = async { tokio::join!(stopping, claiming).0 };
+            match dashboard {
+                Some(dashboard) =>
+                    tokio::select! {
+                        error = running => error,
+                        _ = self.relay_rpc_events(dashboard) => unreachable!(),
+                    },
+                None => running.await,
+            }
+        };
 
-            last_hash_at = proof.last_hash_at;
-            last_balance = proof.balance;
-
-            let solution = Self::find_hash_par(
-                proof.challenge,
-                self.get_cutoff(proof.last_hash_at, args.buffer_time).await,
-                args.cores,
-                config.min_difficulty as u32,
-                nonce_indices.as_slice()
-            ).await;
-
-            // Submit transaction
-            self.send_and_confirm(
-                &[
-                    ore_api::instruction::auth(proof_pubkey(miner)),
-                    mine(
-                        signer.pubkey(),
-                        Pubkey::from_str("5nsXYepY5h8LfbkE8aT79oy5w9eDSTJDUMf345JQdWJ9").unwrap(),
-                        Pubkey::from_str("6btvikiSJwq7rArfD9s77g1EBnurMFQ1rxBwUfxY2jU8").unwrap(),
-                        self.find_bus().await,
-                        solution
-                    ),
-                ]
-            ).await.ok();
+        // Serve control requests on this task alongside the mining loop, since claims need the
+        // miner. Requests in flight when mining ends are still answered.
+        let mining_done = Cell::new(false);
+        let mining = async {
+            let error = mining.await;
+            mining_done.set(true);
+            error
+        };
+        let error = match &control_socket {
+            Some(socket) =>
+                tokio::join!(
+                    mining,
+                    self.serve_control(socket, &control, &session, dashboard, &mining_done)
+                ).0,
+            None => mining.await,
+        };
+        drop(control_socket);
+
+        if let Some(dashboard) = dashboard {
+            dashboard.close();
+        }
+        for line in deferred.take() {
+            println!("{}", line);
+        }
+        if let Some(err) = error {
+            eprintln!("{} Cannot continue mining: {}", "ERROR".bold().red(), err);
+        }
+        println!("\n{}\n{}", "Session summary".bold(), session.borrow().summary());
+        if error.is_some() {
+            std::process::exit(1);
         }
     }
 
+    /// Hashes the challenge on every thread of the pool until the cutoff, or until hashing is
+    /// halted, and returns the best solution. Progress is shown on the dashboard if it is open,
+    /// and with a spinner otherwise.
     async fn find_hash_par(
+        pool: &HashPool,
         challenge: [u8; 32],
         cutoff_time: u64,
-        cores: u64,
         min_diff: u32,
-        nonce_indices: &[u64]
-    ) -> Solution {
-        let progress_bar = Arc::new(spinner::new_progress_bar());
-        let global_best_difficulty = Arc::new(AtomicU32::new(0));
-
-        progress_bar.set_message("Mining...");
-
-        let core_ids = core_affinity::get_core_ids().unwrap();
-        let core_ids = core_ids.into_iter().take(cores as usize);
-
-        let handles: Vec<_> = core_ids
-            .enumerate()
-            .map(|(id, core)| {
-                let global_best_difficulty = Arc::clone(&global_best_difficulty);
-                let progress_bar = Arc::clone(&progress_bar);
-                let nonce = nonce_indices[id];
-
-                tokio::spawn(async move {
-                    let mut memory = equix::SolverMemory::new();
-                    let _ = core_affinity::set_for_current(core);
-
-                    let timer = Instant::now();
-                    let mut nonce = nonce;
-                    let mut best_result = (nonce, 0u32, Hash::default());
-
-                    loop {
-                        let hxs = drillx::hashes_with_memory(
-                            &mut memory,
-                            &challenge,
-                            &nonce.to_le_bytes()
-                        );
-
-                        for hx in hxs {
-                            let difficulty = hx.difficulty();
-                            if difficulty > best_result.1 {
-                                best_result = (nonce, difficulty, hx);
-                                global_best_difficulty.fetch_max(difficulty, Ordering::Relaxed);
-                            }
-                        }
-                        let global_best = global_best_difficulty.load(Ordering::Relaxed);
-
-                        let elapsed = timer.elapsed().as_secs();
-
-                        let remaining = cutoff_time.saturating_sub(elapsed);
-
-                        progress_bar.set_message(
-                            format!(
-                                "Mining... (difficulty {}, time {})",
-                                global_best,
-                                format_duration(remaining as u32)
-                            )
-                        );
-
-                        if elapsed >= cutoff_time && global_best >= min_diff {
-                            break;
-                        }
-
-                        nonce += 1;
-                    }
-
-                    best_result
-                })
-            })
-            .collect();
+        control: &MineControl,
+        session: &RefCell<Session>,
+        dashboard: Option<&Dashboard>
+    ) -> (Solution, u32) {
+        let cutoff = Instant::now() + Duration::from_secs(cutoff_time);
+        let telemetry = pool.telemetry();
+        let hash = pool.hash(
+            challenge,
+            cutoff,
+            min_diff,
+            Some(control.halt_flag()),
+            Arc::clone(&telemetry)
+        );
 
-        let results = futures::future::join_all(handles).await;
+        // The dashboard draws the telemetry itself, otherwise a spinner shows it
+        let results = match dashboard {
+            Some(dashboard) => {
+                dashboard.start_round(cutoff, min_diff, Arc::clone(&telemetry));
+                let results = hash.await;
+                dashboard.finish_round();
+                results
+            }
+            None => {
+                let progress_bar = spinner::new_progress_bar();
+                progress_bar.set_message("Mining...");
+                let results = tokio::select! {
+                    results = hash => results,
+                    _ = report_progress(&progress_bar, &telemetry, cutoff) => unreachable!(),
+                };
+                progress_bar.finish_and_clear();
+                results
+            }
+        };
         let best_result = results
             .into_iter()
-            .filter_map(Result::ok)
-            .max_by_key(|&(_, difficulty, _)| difficulty)
-            .unwrap_or((0, 0, Hash::default()));
+            .max_by_key(|result| result.difficulty)
+            .unwrap_or_default();
+
+        session.borrow_mut().record_round(&telemetry);
+        report(dashboard, format!("Hashrate: {}", telemetry.hashrate_summary()));
+        report(
+            dashboard,
+            format!("Difficulties: {}", telemetry.difficulty_summary(DIFFICULTY_SUMMARY_LEVELS))
+        );
+        report(
+            dashboard,
+            format!(
+                "Best hash: {} (difficulty {})",
+                bs58::encode(best_result.hash.h).into_string(),
+                best_result.difficulty
+            )
+        );
 
-        progress_bar.finish();
+        (
+            Solution::new(best_result.hash.d, best_result.nonce.to_le_bytes()),
+            best_result.difficulty as u32,
+        )
+    }
 
-        println!(
-            "Best hash: {} (difficulty {})",
-            bs58::encode(best_result.2.h).into_string(),
-            best_result.1
-        );
+    /// Moves RPC health changes onto the dashboard log at a fixed rate, since printing them
+    /// would draw over the dashboard.
+    async fn relay_rpc_events(&self, dashboard: &Dashboard) {
+        let mut interval = tokio::time::interval(REPORT_INTERVAL);
+        loop {
+            interval.tick().await;
+            for event in self.rpc_pool.take_events() {
+                dashboard.log(event);
+            }
+        }
+    }
 
-        Solution::new(best_result.2.d, best_result.0.to_le_bytes())
+    /// Submits a solution, recording it on the dashboard if it is open.
+    async fn submit_solution(
+        &self,
+        ixs: &[Instruction],
+        difficulty: u32,
+        dashboard: Option<&Dashboard>
+    ) -> Result<(), SendError> {
+        let Some(dashboard) = dashboard else {
+            return self.send_and_confirm(ixs).await;
+        };
+        let started = Instant::now();
+        let result = self.send_and_confirm_silently(ixs).await;
+        let submission = Submission {
+            signature: result.as_ref().ok().copied(),
+            difficulty,
+            status: match &result {
+                Ok(_) => "Confirmed".to_string(),
+                Err(err) => err.to_string(),
+            },
+            latency: started.elapsed(),
+        };
+        let tip = *self.tip.read().unwrap();
+        dashboard.add_submission(submission, tip, self.last_priority_fee.get());
+        result.map(|_| ())
     }
 
     pub fn check_num_cores(&self, cores: u64) {
@@ -197,43 +493,131 @@ impl Miner {
             .max(0) as u64
     }
 
-    async fn find_bus(&self) -> Pubkey {
+    /// Returns true if the epoch has ended, or is about to, and the buses need to be reset.
+    async fn needs_reset(&self) -> bool {
+        let config = get_config(&self.rpc_client).await;
+        let clock = get_clock(&self.rpc_client).await;
+        config.last_reset_at
+            .saturating_add(EPOCH_DURATION)
+            .saturating_sub(TOLERANCE)
+            .le(&clock.unix_timestamp)
+    }
+
+    /// Picks a bus to mine on with the given strategy, skipping buses that were already tried.
+    /// A reset refills every bus, so any bus will do when resetting. The recent write activity
+    /// is only fetched if the strategy needs it, and once per round.
+    async fn find_bus(
+        &self,
+        strategy: BusStrategy,
+        reward: u64,
+        exclude: &[Pubkey],
+        reset: bool,
+        last_write_slots: &mut Option<[u64; BUS_COUNT]>
+    ) -> Pubkey {
+        let mut rng = rand::thread_rng();
+        let indexes: Vec<usize> = (0..BUS_COUNT)
+            .filter(|index| !exclude.contains(&BUS_ADDRESSES[*index]))
+            .collect();
+        if indexes.is_empty() {
+            return Self::get_random_bus();
+        }
+        if reset {
+            return BUS_ADDRESSES[indexes[rng.gen_range(0..indexes.len())]];
+        }
+
         let accounts = match self.rpc_client.get_multiple_accounts(&BUS_ADDRESSES).await {
             Ok(accounts) => accounts,
             Err(_) => {
                 return Self::get_random_bus();
             }
         };
-
-        accounts
+        let last_write_slots = match (strategy.needs_activity(), *last_write_slots) {
+            (false, _) => [0; BUS_COUNT],
+            (true, Some(slots)) => slots,
+            (true, None) => *last_write_slots.insert(self.get_last_write_slots().await),
+        };
+        let candidates: Vec<BusCandidate> = indexes
             .into_iter()
-            .enumerate()
-            .filter_map(|(index, account)| {
-                account.and_then(|acc| {
-                    Bus::try_from_bytes(&acc.data)
-                        .ok()
-                        .map(|bus| (index, bus.rewards))
+            .filter_map(|index| {
+                let account = accounts.get(index)?.as_ref()?;
+                let bus = Bus::try_from_bytes(&account.data).ok()?;
+                Some(BusCandidate {
+                    index,
+                    rewards: bus.rewards,
+                    last_write_slot: last_write_slots[index],
                 })
             })
-            .max_by_key(|&(_, rewards)| rewards)
-            .map(|(index, _)| BUS_ADDRESSES[index])
+            .collect();
+
+        strategy
+            .select(&candidates, reward, &mut rng)
+            .map(|index| BUS_ADDRESSES[index])
             .unwrap_or_else(Self::get_random_bus)
     }
 
+    /// Returns true if the bus can no longer pay the reward in full. Unknown buses count as not
+    /// exhausted, so a failure is not retried blindly.
+    async fn is_bus_exhausted(&self, bus: Pubkey, reward: u64) -> bool {
+        let Ok(data) = self.rpc_client.get_account_data(&bus).await else {
+            return false;
+        };
+        Bus::try_from_bytes(&data).map_or(false, |bus| bus.rewards.lt(&reward))
+    }
+
+    /// Returns the slot of the most recent transaction to write to each bus, or 0 if unknown.
+    async fn get_last_write_slots(&self) -> [u64; BUS_COUNT] {
+        let slots = join_all(
+            BUS_ADDRESSES.iter().map(|address| {
+                self.rpc_client.get_signatures_for_address_with_config(
+                    address,
+                    GetConfirmedSignaturesForAddress2Config {
+                        limit: Some(1),
+                        ..Default::default()
+                    }
+                )
+            })
+        ).await;
+        let mut last_write_slots = [0; BUS_COUNT];
+        for (index, signatures) in slots.into_iter().enumerate() {
+            if let Some(signature) = signatures.ok().and_then(|s| s.into_iter().next()) {
+                last_write_slots[index] = signature.slot;
+            }
+        }
+        last_write_slots
+    }
+
     fn get_random_bus() -> Pubkey {
         let i = rand::thread_rng().gen_range(0..BUS_COUNT);
         BUS_ADDRESSES[i]
     }
 }
 
-fn calculate_multiplier(balance: u64, top_balance: u64) -> f64 {
-    1.0 + ((balance as f64) / (top_balance as f64)).min(1.0f64)
+/// Shows the best difficulty, the time left and the hashrate on the spinner at a fixed rate,
+/// until the round is dropped.
+async fn report_progress(progress_bar: &ProgressBar, telemetry: &Telemetry, cutoff: Instant) {
+    let mut interval = tokio::time::interval(REPORT_INTERVAL);
+    loop {
+        interval.tick().await;
+        let remaining = cutoff.saturating_duration_since(Instant::now()).as_secs();
+        progress_bar.set_message(
+            format!(
+                "Mining... (difficulty {}, time {:02}:{:02}, {:.0} H/s)",
+                telemetry.best_difficulty(),
+                remaining / 60,
+                remaining % 60,
+                telemetry.hashrate()
+            )
+        );
+    }
 }
 
-fn format_duration(seconds: u32) -> String {
-    let minutes = seconds / 60;
-    let remaining_seconds = seconds % 60;
-    format!("{:02}:{:02}", minutes, remaining_seconds)
+/// Returns the most a solution of the given difficulty can earn, before liveness penalties.
+pub fn expected_reward(config: &Config, balance: u64, difficulty: u32) -> u64 {
+    let base = config.base_reward_rate
+        .saturating_mul(
+            2u64.saturating_pow(difficulty.saturating_sub(config.min_difficulty as u32))
+        );
+    ((base as f64) * calculate_multiplier(balance, config.top_balance)) as u64
 }
 
 #[repr(C)]
@@ -244,10 +628,8 @@ pub struct Mine {
 }
 
 impl Mine {
-    fn to_bytes(&self) -> Vec<u8> {
-        let discriminator: [u8; 8] = [170, 66, 140, 123, 15, 20, 224, 194];
-
-        [discriminator.as_slice(), bytemuck::bytes_of(self)].concat()
+    fn to_bytes(self) -> Vec<u8> {
+        [MINE_DISCRIMINATOR.as_slice(), bytemuck::bytes_of(&self)].concat()
     }
 }
 
@@ -259,7 +641,7 @@ pub fn mine(
     solution: Solution
 ) -> Instruction {
     Instruction {
-        program_id: Pubkey::from_str("tPCXnizACpbtbiAn4NQjvbKC9Tp28BBvkjhaSgqDfhH").unwrap(),
+        program_id: POOL_PROGRAM_ID,
         accounts: vec![
             AccountMeta::new(signer, true),
             AccountMeta::new(pool, false),
@@ -0,0 +1,80 @@
+use std::str::FromStr;
+
+use solana_program::{nonce::State, pubkey::Pubkey, system_instruction, system_program};
+
+use crate::{
+    args::{NonceArgs, NonceCommand, NonceCreateArgs, NonceShowArgs},
+    exit_with_error,
+    utils::get_nonce_data,
+    Miner,
+};
+
+/// The seed `ore nonce create` derives the nonce account from by default.
+const DEFAULT_SEED: &str = "ore-nonce";
+
+impl Miner {
+    pub async fn nonce(&self, args: NonceArgs) {
+        match args.command {
+            NonceCommand::Create(args) => self.nonce_create(args).await,
+            NonceCommand::Show(args) => self.nonce_show(args).await,
+        }
+    }
+
+    async fn nonce_create(&self, args: NonceCreateArgs) {
+        let signer = self.signer();
+        let address = nonce_address(signer.pubkey(), &args.seed);
+        if self.rpc_client.get_account(&address).await.is_ok() {
+            exit_with_error(&format!("Nonce account {} already exists", address));
+        }
+        let authority = match args.authority {
+            Some(authority) => Pubkey::from_str(&authority)
+                .unwrap_or_else(|_| exit_with_error(&format!("Invalid authority: {}", authority))),
+            None => signer.pubkey(),
+        };
+        let lamports = self
+            .rpc_client
+            .get_minimum_balance_for_rent_exemption(State::size())
+            .await
+            .unwrap_or_else(|err| exit_with_error(&format!("Failed to fetch rent: {}", err)));
+
+        // Derive the account from the signer so no extra keypair has to sign
+        let ixs = system_instruction::create_nonce_account_with_seed(
+            &signer.pubkey(),
+            &address,
+            &signer.pubkey(),
+            &args.seed,
+            &authority,
+            lamports,
+        );
+        if self.send_and_confirm_with_blockhash(&ixs).await.is_ok() {
+            println!("Created nonce account {}", address);
+            println!("Pass --nonce {} to sign transactions with it.", address);
+        }
+    }
+
+    async fn nonce_show(&self, args: NonceShowArgs) {
+        let address = match args.address {
+            Some(address) => Pubkey::from_str(&address)
+                .unwrap_or_else(|_| exit_with_error(&format!("Invalid address: {}", address))),
+            None => self
+                .nonce
+                .unwrap_or_else(|| nonce_address(self.signer().pubkey(), DEFAULT_SEED)),
+        };
+        let data = get_nonce_data(&self.rpc_client, address)
+            .await
+            .unwrap_or_else(|err| exit_with_error(&err));
+        println!("Address: {}", address);
+        println!("Authority: {}", data.authority);
+        println!("Nonce: {}", data.blockhash());
+        println!(
+            "Fee per signature: {} lamports",
+            data.get_lamports_per_signature()
+        );
+    }
+}
+
+/// Returns the address of the nonce account derived from `base` and `seed`.
+fn nonce_address(base: Pubkey, seed: &str) -> Pubkey {
+    Pubkey::create_with_seed(&base, seed, &system_program::id())
+        .unwrap_or_else(|err| exit_with_error(&format!("Invalid seed {}: {}", seed, err)))
+}
@@ -0,0 +1,78 @@
+use std::str::FromStr;
+
+use solana_program::{hash::Hash, instruction::Instruction, system_instruction};
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction, signature::Signer, transaction::Transaction,
+};
+
+use crate::{
+    args::OfflineArgs,
+    cu_limits::{default_cu_limit, CU_LIMIT_DEFAULT},
+    exit_with_error,
+    send_and_confirm::PRIORITY_FEE,
+    Miner,
+};
+
+impl Miner {
+    /// Submits the instructions, or with `--sign-only` signs them and prints the transaction for
+    /// `ore submit`.
+    pub async fn send_or_sign(&self, ixs: Vec<Instruction>, args: &OfflineArgs) {
+        if !args.sign_only {
+            self.send_and_confirm(&ixs).await.ok();
+            return;
+        }
+        let tx = self
+            .sign_offline(&ixs, args)
+            .unwrap_or_else(|err| exit_with_error(&err));
+        let encoded = bs58::encode(bincode::serialize(&tx).unwrap()).into_string();
+        match &args.out {
+            Some(path) => {
+                std::fs::write(path, format!("{}\n", encoded)).unwrap_or_else(|err| {
+                    exit_with_error(&format!("Failed to write {}: {}", path, err))
+                });
+                eprintln!(
+                    "Signed transaction {} written to {}",
+                    tx.signatures[0], path
+                );
+            }
+            None => println!("{}", encoded),
+        }
+    }
+
+    /// Signs the instructions with the given blockhash, without touching the network. With a
+    /// durable nonce the transaction advances the nonce first, as the runtime requires.
+    fn sign_offline(&self, ixs: &[Instruction], args: &OfflineArgs) -> Result<Transaction, String> {
+        let blockhash = args.blockhash.as_deref().unwrap_or_default();
+        let blockhash =
+            Hash::from_str(blockhash).map_err(|_| format!("Invalid blockhash: {}", blockhash))?;
+
+        // Build the transaction, advancing the nonce first
+        let signer = self.signer();
+        let fee_payer = self.fee_payer();
+        let mut signers: Vec<&dyn Signer> = vec![signer, fee_payer];
+        let mut final_ixs = vec![];
+        if let Some(nonce) = self.nonce {
+            let authority = self.nonce_authority();
+            final_ixs.push(system_instruction::advance_nonce_account(
+                &nonce,
+                &authority.pubkey(),
+            ));
+            signers.push(authority);
+        }
+        let cu_limit_index = final_ixs.len();
+        final_ixs.push(ComputeBudgetInstruction::set_compute_unit_limit(
+            CU_LIMIT_DEFAULT,
+        ));
+        final_ixs.push(ComputeBudgetInstruction::set_compute_unit_price(
+            PRIORITY_FEE,
+        ));
+        final_ixs.extend_from_slice(ixs);
+        final_ixs[cu_limit_index] =
+            ComputeBudgetInstruction::set_compute_unit_limit(default_cu_limit(&final_ixs));
+
+        let mut tx = Transaction::new_with_payer(&final_ixs, Some(&fee_payer.pubkey()));
+        tx.try_sign(&signers, blockhash)
+            .map_err(|err| format!("Failed to sign transaction: {}", err))?;
+        Ok(tx)
+    }
+}
@@ -1,11 +1,16 @@
-use std::str::FromStr;
+use std::{ str::FromStr, time::{ Duration, Instant } };
 
-use ore_api::consts::TOKEN_DECIMALS;
+use ore_api::{ consts::{ CONFIG_ADDRESS, TOKEN_DECIMALS }, state::{ Config, Proof } };
+use ore_utils::AccountDeserialize;
 use solana_program::pubkey::Pubkey;
-use solana_sdk::signature::Signer;
 use spl_token::amount_to_ui_amount;
 
-use crate::{ args::ProofArgs, utils::{ get_proof, proof_pubkey, get_config }, Miner };
+use crate::{
+    args::ProofArgs,
+    utils::{ calculate_multiplier, get_proof, proof_pubkey, get_config },
+    watch::proof_activity,
+    Miner,
+};
 
 impl Miner {
     pub async fn proof(&self, args: ProofArgs) {
@@ -17,33 +22,57 @@ impl Miner {
             proof_pubkey(signer.pubkey())
         };
 
+        if let Some(interval) = args.watch {
+            self.watch_proof(address, Duration::from_secs(interval.max(1))).await;
+            return;
+        }
+
         print!("Fetching proof for address {}... ", address);
 
         let proof = get_proof(&self.rpc_client, address).await;
 
         let config = get_config(&self.rpc_client).await;
 
-        let multiplier = calculate_multiplier(proof.balance, config.top_balance);
-
         println!("Address: {:?}", address);
-        println!("Authority: {:?}", proof.authority);
-        println!("Multiplier {:?}", multiplier);
-        println!("Balance: {:?} ORE", amount_to_ui_amount(proof.balance, TOKEN_DECIMALS));
-        println!(
-            "Last hash: {}",
-            solana_sdk::hash::Hash::new_from_array(proof.last_hash).to_string()
-        );
-        println!("Last hash at: {:?}", proof.last_hash_at);
-        println!("Last stake at: {:?}", proof.last_stake_at);
-        println!("Miner: {:?}", proof.miner);
-        println!("Total hashes: {:?}", proof.total_hashes);
-        println!(
-            "Total rewards: {:?} ORE",
-            amount_to_ui_amount(proof.total_rewards, TOKEN_DECIMALS)
-        );
+        print!("{}", describe_proof(&proof, &config));
+    }
+
+    /// Redraws the proof as it changes, along with its activity since watching started.
+    async fn watch_proof(&self, address: Pubkey, interval: Duration) {
+        let started = Instant::now();
+        let mut start = None;
+        self.watch_accounts(&[address, CONFIG_ADDRESS], interval, |accounts| {
+            let proof = accounts[0].as_deref().and_then(|data| Proof::try_from_bytes(data).ok());
+            let config = accounts[1].as_deref().and_then(|data| Config::try_from_bytes(data).ok());
+            let (Some(proof), Some(config)) = (proof, config) else {
+                return format!("Address: {}\nProof not found\n", address);
+            };
+            let start = start.get_or_insert(*proof);
+            format!(
+                "Address: {}\n{}\n{}",
+                address,
+                describe_proof(proof, config),
+                proof_activity(start, proof, started.elapsed())
+            )
+        }).await;
     }
 }
 
-fn calculate_multiplier(balance: u64, top_balance: u64) -> f64 {
-    1.0 + ((balance as f64) / (top_balance as f64)).min(1.0f64)
+fn describe_proof(proof: &Proof, config: &Config) -> String {
+    let multiplier = calculate_multiplier(proof.balance, config.top_balance);
+    [
+        format!("Authority: {:?}", proof.authority),
+        format!("Multiplier {:?}", multiplier),
+        format!("Balance: {:?} ORE", amount_to_ui_amount(proof.balance, TOKEN_DECIMALS)),
+        format!("Last hash: {}", solana_sdk::hash::Hash::new_from_array(proof.last_hash)),
+        format!("Last hash at: {:?}", proof.last_hash_at),
+        format!("Last stake at: {:?}", proof.last_stake_at),
+        format!("Miner: {:?}", proof.miner),
+        format!("Total hashes: {:?}", proof.total_hashes),
+        format!(
+            "Total rewards: {:?} ORE",
+            amount_to_ui_amount(proof.total_rewards, TOKEN_DECIMALS)
+        ),
+        String::new(),
+    ].join("\n")
 }
@@ -0,0 +1,315 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use colored::*;
+use futures::{future::join_all, stream::FuturesUnordered, StreamExt};
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind, Result as ClientResult},
+    nonblocking::rpc_client::RpcClient,
+    rpc_custom_error::JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY,
+    rpc_request::{RpcError, RpcRequest},
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+use solana_rpc_client::rpc_client::RpcClientConfig;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    signature::Signature,
+    transaction::{Result as TransactionResult, Transaction},
+};
+
+/// How often endpoints are health checked.
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How far an endpoint may fall behind the highest observed slot before it is unhealthy.
+const MAX_SLOT_LAG: u64 = 50;
+
+/// A single RPC endpoint and its measured health.
+pub struct Endpoint {
+    pub client: Arc<RpcClient>,
+    healthy: AtomicBool,
+    /// A moving average of the request latency, in microseconds.
+    latency: AtomicU64,
+    slot: AtomicU64,
+}
+
+impl Endpoint {
+    pub fn url(&self) -> String {
+        self.client.url()
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+
+    pub fn latency(&self) -> Duration {
+        Duration::from_micros(self.latency.load(Ordering::Relaxed))
+    }
+
+    fn record_latency(&self, elapsed: Duration) {
+        let sample = elapsed.as_micros() as u64;
+        let latency = match self.latency.load(Ordering::Relaxed) {
+            0 => sample,
+            latency => (latency * 3 + sample) / 4,
+        };
+        self.latency.store(latency, Ordering::Relaxed);
+    }
+}
+
+/// A set of RPC endpoints. Reads fail over to the next best endpoint, and transactions are
+/// broadcast to every healthy endpoint at once.
+pub struct RpcPool {
+    endpoints: Vec<Endpoint>,
+    /// Health changes waiting to be shown, while they are captured instead of printed.
+    events: Mutex<Option<Vec<String>>>,
+}
+
+impl RpcPool {
+    pub fn new(urls: Vec<String>, commitment: CommitmentConfig) -> Arc<Self> {
+        let endpoints = urls
+            .into_iter()
+            .map(|url| Endpoint {
+                client: Arc::new(RpcClient::new_with_commitment(url, commitment)),
+                healthy: AtomicBool::new(true),
+                latency: AtomicU64::new(0),
+                slot: AtomicU64::new(0),
+            })
+            .collect();
+        Arc::new(Self {
+            endpoints,
+            events: Mutex::new(None),
+        })
+    }
+
+    /// Holds health changes back for `take_events` instead of printing them to stderr, so they
+    /// do not draw over the dashboard.
+    pub fn capture_events(&self) {
+        self.events.lock().unwrap().get_or_insert_with(Vec::new);
+    }
+
+    /// Returns the health changes captured since the last call.
+    pub fn take_events(&self) -> Vec<String> {
+        self.events
+            .lock()
+            .unwrap()
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    fn record_success(&self, endpoint: &Endpoint, elapsed: Duration) {
+        endpoint.record_latency(elapsed);
+        self.set_healthy(endpoint, true);
+    }
+
+    fn set_healthy(&self, endpoint: &Endpoint, healthy: bool) {
+        if endpoint
+            .healthy
+            .swap(healthy, Ordering::Relaxed)
+            .eq(&healthy)
+        {
+            return;
+        }
+        let event = if healthy {
+            format!("RPC {} is healthy again", endpoint.url())
+        } else {
+            format!(
+                "{} RPC {} is unhealthy",
+                "WARNING".bold().yellow(),
+                endpoint.url()
+            )
+        };
+        match self.events.lock().unwrap().as_mut() {
+            Some(events) => events.push(event),
+            None => eprintln!("{}", event),
+        }
+    }
+
+    /// Returns an rpc client that sends each request to the best endpoint, failing over to the
+    /// others on transport errors.
+    pub fn client(self: &Arc<Self>, commitment: CommitmentConfig) -> RpcClient {
+        RpcClient::new_sender(
+            FailoverSender {
+                pool: Arc::clone(self),
+            },
+            RpcClientConfig::with_commitment(commitment),
+        )
+    }
+
+    /// Returns every endpoint, healthy ones first and ordered by latency.
+    pub fn ranked(&self) -> Vec<&Endpoint> {
+        let mut endpoints: Vec<&Endpoint> = self.endpoints.iter().collect();
+        endpoints.sort_by_key(|endpoint| (!endpoint.is_healthy(), endpoint.latency()));
+        endpoints
+    }
+
+    /// Returns the healthy endpoints, or every endpoint if none are healthy.
+    pub fn healthy(&self) -> Vec<&Endpoint> {
+        let healthy: Vec<&Endpoint> = self
+            .endpoints
+            .iter()
+            .filter(|endpoint| endpoint.is_healthy())
+            .collect();
+        if healthy.is_empty() {
+            self.endpoints.iter().collect()
+        } else {
+            healthy
+        }
+    }
+
+    /// Periodically measures the latency and slot of each endpoint in the background.
+    pub fn spawn_health_checks(self: &Arc<Self>) {
+        if self.endpoints.len() < 2 {
+            return;
+        }
+        let pool = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                pool.check_health().await;
+            }
+        });
+    }
+
+    async fn check_health(&self) {
+        join_all(self.endpoints.iter().map(|endpoint| async move {
+            let timer = Instant::now();
+            match endpoint.client.get_slot().await {
+                Ok(slot) => {
+                    endpoint.slot.store(slot, Ordering::Relaxed);
+                    self.record_success(endpoint, timer.elapsed());
+                }
+                Err(_) => self.set_healthy(endpoint, false),
+            }
+        }))
+        .await;
+
+        // Endpoints that fall too far behind the others are unhealthy too.
+        let max_slot = self
+            .endpoints
+            .iter()
+            .map(|endpoint| endpoint.slot.load(Ordering::Relaxed))
+            .max()
+            .unwrap_or(0);
+        for endpoint in &self.endpoints {
+            if endpoint.slot.load(Ordering::Relaxed) + MAX_SLOT_LAG < max_slot {
+                self.set_healthy(endpoint, false);
+            }
+        }
+    }
+
+    /// Sends a transaction to every healthy endpoint, and the extra client if given, at once.
+    /// Returns as soon as any of them accepts it, while the others keep sending in the
+    /// background.
+    pub async fn send_transaction(
+        &self,
+        tx: &Transaction,
+        extra: Option<&Arc<RpcClient>>,
+    ) -> ClientResult<Signature> {
+        let mut sends: FuturesUnordered<_> = self
+            .healthy()
+            .into_iter()
+            .map(|endpoint| &endpoint.client)
+            .chain(extra)
+            .map(|client| {
+                let client = Arc::clone(client);
+                let tx = tx.clone();
+                tokio::spawn(async move { client.send_transaction(&tx).await })
+            })
+            .collect();
+        let mut error = None;
+        while let Some(result) = sends.next().await {
+            match result {
+                Ok(Ok(signature)) => return Ok(signature),
+                Ok(Err(err)) => error = Some(err),
+                Err(err) => error = Some(ClientErrorKind::Custom(err.to_string()).into()),
+            }
+        }
+        Err(error.unwrap_or_else(|| ClientErrorKind::Custom("No RPC endpoints".into()).into()))
+    }
+
+    /// Returns the status of a transaction from whichever endpoint reports it first, or none if
+    /// no endpoint has seen it reach the commitment level yet.
+    pub async fn get_signature_status(
+        &self,
+        signature: &Signature,
+        commitment: CommitmentConfig,
+    ) -> Option<TransactionResult<()>> {
+        let mut statuses: FuturesUnordered<_> = self
+            .healthy()
+            .into_iter()
+            .map(|endpoint| {
+                endpoint
+                    .client
+                    .get_signature_status_with_commitment(signature, commitment)
+            })
+            .collect();
+        while let Some(status) = statuses.next().await {
+            if let Ok(Some(status)) = status {
+                return Some(status);
+            }
+        }
+        None
+    }
+}
+
+/// An rpc transport that tries each endpoint of a pool in order of health and latency.
+struct FailoverSender {
+    pool: Arc<RpcPool>,
+}
+
+#[async_trait]
+impl RpcSender for FailoverSender {
+    async fn send(
+        &self,
+        request: RpcRequest,
+        params: serde_json::Value,
+    ) -> ClientResult<serde_json::Value> {
+        let mut error = None;
+        for endpoint in self.pool.ranked() {
+            let timer = Instant::now();
+            match endpoint
+                .client
+                .send::<serde_json::Value>(request, params.clone())
+                .await
+            {
+                Ok(result) => {
+                    self.pool.record_success(endpoint, timer.elapsed());
+                    return Ok(result);
+                }
+                Err(err) if is_endpoint_error(&err) => {
+                    self.pool.set_healthy(endpoint, false);
+                    error = Some(err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Err(error.unwrap_or_else(|| ClientErrorKind::Custom("No RPC endpoints".into()).into()))
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        RpcTransportStats::default()
+    }
+
+    fn url(&self) -> String {
+        self.pool.ranked()[0].url()
+    }
+}
+
+/// Returns true if the error is caused by the endpoint rather than the request, so another
+/// endpoint may succeed.
+fn is_endpoint_error(err: &ClientError) -> bool {
+    match err.kind() {
+        ClientErrorKind::Io(_) | ClientErrorKind::Reqwest(_) => true,
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) => {
+            code.eq(&JSON_RPC_SERVER_ERROR_NODE_UNHEALTHY)
+        }
+        _ => false,
+    }
+}
@@ -1,113 +1,484 @@
-use std::str::FromStr;
+use std::{ str::FromStr, time::{ Duration, Instant } };
 
+use indicatif::ProgressBar;
 use rand::seq::SliceRandom;
-use solana_client::client_error::Result as ClientResult;
-use solana_program::{ instruction::Instruction, pubkey::Pubkey, system_instruction::transfer };
+use solana_client::{
+    client_error::{ ClientError, Result as ClientResult },
+    rpc_config::RpcSimulateTransactionConfig,
+};
+use solana_program::{
+    hash::Hash,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    system_instruction::{ self, transfer },
+};
 use solana_rpc_client::spinner;
 use solana_sdk::{
     compute_budget::ComputeBudgetInstruction,
-    signature::Signer,
-    transaction::Transaction,
+    signature::Signature,
+    transaction::{ uses_durable_nonce, Transaction, TransactionError },
 };
 
-use crate::Miner;
+use crate::{
+    cu_limits::{ default_cu_limit, CU_LIMIT_BUILTIN, CU_LIMIT_DEFAULT },
+    error::SendError,
+    utils::get_nonce_data,
+    Miner,
+};
 
+/// The number of times a transaction is signed with a fresh blockhash before giving up.
 const MAX_RETRIES: u32 = 5;
 
+/// How often a pending transaction is rebroadcast.
+const REBROADCAST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the status of a pending transaction is polled.
+const CONFIRM_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long a signed transaction is rebroadcast before giving up on it, unless it is waiting
+/// for its blockhash to expire.
+const SIGNED_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// How long a blockhash transaction is rebroadcast while its expiry cannot be confirmed, before
+/// its outcome is reported as unknown. Blockhashes normally expire in about a minute.
+const EXPIRY_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// The margin added to simulated compute units, in percent.
+const CU_MARGIN_PERCENT: u64 = 10;
+
+/// The Jito tip accounts. Tips are paid to one of these at random.
+pub const JITO_TIP_ACCOUNTS: [&str; 8] = [
+    "96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5",
+    "HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe",
+    "Cw8CFyM9FkoMi7K7Crf6HNQqf4uEMzpKw6QNghXLvLkY",
+    "ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49",
+    "DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh",
+    "ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt",
+    "DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL",
+    "3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT",
+];
+
+/// The priority fee paid per compute unit, in microlamports.
+pub const PRIORITY_FEE: u64 = 10_000;
+
+/// The highest priority fee paid with dynamic fees, in microlamports.
+pub const MAX_PRIORITY_FEE: u64 = 100_000;
+
 impl Miner {
-    pub async fn send_and_confirm(&self, ixs: &[Instruction]) -> Result<(), ()> {
-        let progress_bar = spinner::new_progress_bar();
-        let signer = self.signer();
-        let client = self.rpc_client.clone();
-        let mut send_client = self.rpc_client.clone();
+    /// Submits the instructions and waits for confirmation. The transaction is rebroadcast
+    /// while its blockhash is valid, and re-signed with a fresh blockhash, tip and priority fee
+    /// once it expires. Failures are printed with decoded ORE errors and log excerpts.
+    pub async fn send_and_confirm(&self, ixs: &[Instruction]) -> Result<(), SendError> {
+        self.submit_with_spinner(ixs, self.nonce).await
+    }
 
-        let jito_tip = *self.tip.read().unwrap();
+    /// Submits the instructions like `send_and_confirm`, but signs with a recent blockhash even
+    /// if a durable nonce is configured, e.g. to create that nonce account.
+    pub async fn send_and_confirm_with_blockhash(
+        &self,
+        ixs: &[Instruction]
+    ) -> Result<(), SendError> {
+        self.submit_with_spinner(ixs, None).await
+    }
 
-        let mut final_ixs = vec![
-            ComputeBudgetInstruction::set_compute_unit_limit(500_000),
-            ComputeBudgetInstruction::set_compute_unit_price(10000)
-        ];
+    async fn submit_with_spinner(
+        &self,
+        ixs: &[Instruction],
+        nonce: Option<Pubkey>
+    ) -> Result<(), SendError> {
+        let progress_bar = spinner::new_progress_bar();
+        let result = self.submit(ixs, nonce, &progress_bar).await;
+        progress_bar.finish_and_clear();
+        match &result {
+            Ok(signature) => println!("Transaction confirmed: {}", signature),
+            Err(err) => err.print(),
+        }
+        result.map(|_| ())
+    }
 
-        if jito_tip > 0 {
-            send_client = self.jito_client.clone();
-            final_ixs.push(self.get_tip_transfer_ix(signer.pubkey(), jito_tip).unwrap());
+    /// Submits the instructions like `send_and_confirm`, but without a spinner or output, so it
+    /// can run alongside the mining spinner.
+    pub async fn send_and_confirm_silently(
+        &self,
+        ixs: &[Instruction]
+    ) -> Result<Signature, SendError> {
+        self.submit(ixs, self.nonce, &ProgressBar::hidden()).await
+    }
+
+    /// Broadcasts a transaction that is already signed and waits for confirmation. The
+    /// transaction is rebroadcast until it lands, or until its blockhash expires or its durable
+    /// nonce advances.
+    pub async fn send_signed(&self, tx: &Transaction) -> Result<(), SendError> {
+        let progress_bar = spinner::new_progress_bar();
+        progress_bar.set_message("Submitting transaction...");
+        let result = self.broadcast_signed(tx).await;
+        progress_bar.finish_and_clear();
+        match &result {
+            Ok(signature) => println!("Transaction confirmed: {}", signature),
+            Err(err) => err.print(),
         }
+        result.map(|_| ())
+    }
 
-        final_ixs.extend_from_slice(ixs);
+    async fn broadcast_signed(&self, tx: &Transaction) -> Result<Signature, SendError> {
+        let commitment = self.rpc_client.commitment();
+        let signature = tx.signatures[0];
+        let message = &tx.message;
+        let ixs: Vec<Instruction> = message.instructions
+            .iter()
+            .map(|ix| {
+                Instruction::new_with_bytes(
+                    *ix.program_id(&message.account_keys),
+                    &ix.data,
+                    vec![]
+                )
+            })
+            .collect();
+        let nonce = uses_durable_nonce(tx)
+            .and_then(|ix| ix.accounts.first())
+            .map(|index| message.account_keys[*index as usize]);
 
-        let (hash, _slot) = client
-            .get_latest_blockhash_with_commitment(self.rpc_client.commitment()).await
-            .unwrap();
+        // Rebroadcast until the transaction lands, can no longer land, or times out
+        let started = Instant::now();
+        let mut expired = false;
+        let mut last_broadcast: Option<Instant> = None;
+        while started.elapsed() < SIGNED_TIMEOUT {
+            if last_broadcast.map_or(true, |time| time.elapsed() >= REBROADCAST_INTERVAL) {
+                last_broadcast = Some(Instant::now());
+                if let Err(err) = self.rpc_pool.send_transaction(tx, None).await {
+                    if is_permanent_error(&err) {
+                        return Err(SendError::from_client_error(err, &ixs));
+                    }
+                }
+                let valid = match nonce {
+                    Some(nonce) =>
+                        get_nonce_data(&self.rpc_client, nonce).await.map_or(true, |data| {
+                            data.blockhash().eq(&message.recent_blockhash)
+                        }),
+                    None =>
+                        self.rpc_client
+                            .is_blockhash_valid(&message.recent_blockhash, commitment).await
+                            .unwrap_or(true),
+                };
+                if !valid {
+                    expired = true;
+                    break;
+                }
+            }
 
-        let mut tx = Transaction::new_with_payer(&final_ixs, Some(&signer.pubkey()));
+            let status = self.rpc_pool.get_signature_status(&signature, commitment).await;
+            if let Some(status) = status {
+                return status
+                    .map(|()| signature)
+                    .map_err(|err| SendError::from_transaction_error(err, &ixs, vec![]));
+            }
+            tokio::time::sleep(CONFIRM_POLL_INTERVAL).await;
+        }
 
-        tx.sign(&[&signer], hash);
+        // The transaction may have landed just before it expired
+        if let Some(status) = self.rpc_pool.get_signature_status(&signature, commitment).await {
+            return status
+                .map(|()| signature)
+                .map_err(|err| SendError::from_transaction_error(err, &ixs, vec![]));
+        }
+        Err(
+            SendError::Other(
+                match (expired, nonce) {
+                    (false, _) => "Timed out waiting for confirmation".to_string(),
+                    (true, Some(_)) => "The durable nonce was advanced".into(),
+                    (true, None) => "The blockhash expired before the transaction landed".into(),
+                }
+            )
+        )
+    }
 
-        progress_bar.set_message(format!("Submitting transaction..."));
-        let mut retry_count = 0;
+    async fn submit(
+        &self,
+        ixs: &[Instruction],
+        nonce: Option<Pubkey>,
+        progress_bar: &ProgressBar
+    ) -> Result<Signature, SendError> {
+        let signer = self.signer();
+        let fee_payer = self.fee_payer();
+        let commitment = self.rpc_client.commitment();
+        let mut signers = vec![signer, fee_payer];
+        if nonce.is_some() {
+            signers.push(self.nonce_authority());
+        }
 
-        loop {
-            match send_client.send_transaction(&tx).await {
-                Ok(signature) => {
-                    println!("Transaction submitted successfully. Signature: {}", signature);
-                    // Wait for confirmation
-                    match
-                        client.confirm_transaction_with_spinner(
-                            &signature,
-                            &hash,
-                            client.commitment()
-                        ).await
-                    {
-                        Ok(_) => {
-                            println!("Mining transaction confirmed successfully");
+        // Size the compute unit limit and catch program errors before paying fees
+        progress_bar.set_message("Simulating transaction...");
+        let mut cu_limit = self.compute_unit_limit(ixs, fee_payer.pubkey()).await?;
+        if nonce.is_some() {
+            // Leave room for advancing the nonce, which is not simulated
+            cu_limit = (cu_limit + CU_LIMIT_BUILTIN).min(CU_LIMIT_DEFAULT);
+        }
 
-                            return Ok(());
-                        }
-                        Err(e) => {
-                            println!("Transaction failed to confirm: {}", e);
-
-                            if
-                                e
-                                    .to_string()
-                                    .contains("This transaction has already been processed")
-                            {
-                                return Ok(());
-                            }
+        for attempt in 1..=MAX_RETRIES {
+            // Build the transaction with the current tip and priority fee. A durable nonce must
+            // be advanced by the first instruction.
+            let jito_tip = *self.tip.read().unwrap();
+            let priority_fee = self.priority_fee(ixs).await;
+            self.last_priority_fee.set(priority_fee);
+            let mut final_ixs = vec![];
+            if let Some(nonce) = nonce {
+                final_ixs.push(
+                    system_instruction::advance_nonce_account(
+                        &nonce,
+                        &self.nonce_authority().pubkey()
+                    )
+                );
+            }
+            final_ixs.push(ComputeBudgetInstruction::set_compute_unit_limit(cu_limit));
+            final_ixs.push(ComputeBudgetInstruction::set_compute_unit_price(priority_fee));
+            let mut jito_client = None;
+            if jito_tip > 0 {
+                jito_client = Some(&self.jito_client);
+                final_ixs.push(self.get_tip_transfer_ix(fee_payer.pubkey(), jito_tip).unwrap());
+            }
+            final_ixs.extend_from_slice(ixs);
+
+            // Sign with a fresh blockhash, or the current nonce value
+            let (hash, last_valid_block_height) = match self.latest_blockhash(nonce).await {
+                Ok(blockhash) => blockhash,
+                Err(err) => {
+                    progress_bar.println(err);
+                    tokio::time::sleep(REBROADCAST_INTERVAL).await;
+                    continue;
+                }
+            };
+            let mut tx = Transaction::new_with_payer(&final_ixs, Some(&fee_payer.pubkey()));
+            tx
+                .try_sign(&signers, hash)
+                .map_err(|err| SendError::Other(format!("Failed to sign transaction: {}", err)))?;
+            let signature = tx.signatures[0];
+            progress_bar.set_message(
+                format!("Submitting transaction... (attempt {} of {})", attempt, MAX_RETRIES)
+            );
+
+            // Rebroadcast until the transaction lands or can no longer land. A durable nonce only
+            // expires once advanced, so a dropped nonce transaction is re-signed after the
+            // timeout, which cannot execute twice since both spend the same nonce. A blockhash
+            // transaction may land until its blockhash expires, so it is only re-signed then.
+            let signed_at = Instant::now();
+            let timeout = if nonce.is_some() { SIGNED_TIMEOUT } else { EXPIRY_TIMEOUT };
+            let mut expired = false;
+            let mut last_broadcast: Option<Instant> = None;
+            while signed_at.elapsed() < timeout {
+                if last_broadcast.map_or(true, |time| time.elapsed() >= REBROADCAST_INTERVAL) {
+                    last_broadcast = Some(Instant::now());
+                    if let Err(err) = self.rpc_pool.send_transaction(&tx, jito_client).await {
+                        if is_permanent_error(&err) {
+                            return Err(SendError::from_client_error(err, &final_ixs));
                         }
                     }
+                    if self.is_expired(nonce, hash, last_valid_block_height).await {
+                        expired = true;
+                        break;
+                    }
                 }
-                Err(e) => {
-                    println!("Mining transaction failed: {}", e);
+
+                let status = self.rpc_pool.get_signature_status(&signature, commitment).await;
+                if let Some(status) = status {
+                    return status
+                        .map(|()| signature)
+                        .map_err(|err| SendError::from_transaction_error(err, &final_ixs, vec![]));
                 }
+                tokio::time::sleep(CONFIRM_POLL_INTERVAL).await;
             }
 
-            retry_count += 1;
-            if retry_count >= MAX_RETRIES {
-                println!("Max retries exceeded. Aborting.");
-
-                return Err(());
+            // The transaction may have landed just before its blockhash expired
+            if let Some(status) = self.rpc_pool.get_signature_status(&signature, commitment).await {
+                return status
+                    .map(|()| signature)
+                    .map_err(|err| SendError::from_transaction_error(err, &final_ixs, vec![]));
+            }
+            if !expired && nonce.is_none() {
+                return Err(
+                    SendError::Other(
+                        format!(
+                            "Outcome of transaction {} is unknown. Check it before retrying.",
+                            signature
+                        )
+                    )
+                );
             }
+            progress_bar.println(match (expired, nonce) {
+                (false, _) => "Timed out waiting for confirmation. Re-signing transaction...",
+                (true, Some(_)) => "Nonce advanced. Re-signing transaction...",
+                (true, None) => "Blockhash expired. Re-signing transaction...",
+            });
+        }
+
+        Err(SendError::Other("Max retries exceeded".to_string()))
+    }
 
-            println!("Retrying... (Attempt {} of {})", retry_count + 1, MAX_RETRIES);
-            // Optional: Add a delay before retrying
-            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+    /// Returns the blockhash to sign with and the last block height it is valid for. With a
+    /// durable nonce this is the current nonce value, which stays valid until it is advanced.
+    async fn latest_blockhash(&self, nonce: Option<Pubkey>) -> Result<(Hash, u64), String> {
+        match nonce {
+            Some(nonce) =>
+                get_nonce_data(&self.rpc_client, nonce).await.map(|data| {
+                    (data.blockhash(), u64::MAX)
+                }),
+            None =>
+                self.rpc_client
+                    .get_latest_blockhash_with_commitment(self.rpc_client.commitment()).await
+                    .map_err(|err| format!("Failed to fetch blockhash: {}", err)),
         }
     }
 
-    fn get_tip_transfer_ix(&self, from: Pubkey, amount: u64) -> ClientResult<Instruction> {
-        const TIPS: [&str; 8] = [
-            "96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5",
-            "HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe",
-            "Cw8CFyM9FkoMi7K7Crf6HNQqf4uEMzpKw6QNghXLvLkY",
-            "ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49",
-            "DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh",
-            "ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt",
-            "DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL",
-            "3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT",
+    /// Returns true once a transaction signed with `hash` can no longer land, because the block
+    /// height passed `last_valid_block_height` or the durable nonce was advanced.
+    async fn is_expired(
+        &self,
+        nonce: Option<Pubkey>,
+        hash: Hash,
+        last_valid_block_height: u64
+    ) -> bool {
+        match nonce {
+            Some(nonce) =>
+                get_nonce_data(&self.rpc_client, nonce).await.map_or(false, |data| {
+                    data.blockhash().ne(&hash)
+                }),
+            None =>
+                self.rpc_client
+                    .get_block_height().await
+                    .map_or(false, |block_height| block_height > last_valid_block_height),
+        }
+    }
+
+    /// Simulates the instructions and returns the compute units they consume plus a margin.
+    /// Falls back to per-instruction defaults if the simulation cannot be run, and fails if a
+    /// program rejects the transaction.
+    async fn compute_unit_limit(
+        &self,
+        ixs: &[Instruction],
+        payer: Pubkey
+    ) -> Result<u32, SendError> {
+        let mut sim_ixs = vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(CU_LIMIT_DEFAULT),
+            ComputeBudgetInstruction::set_compute_unit_price(PRIORITY_FEE)
         ];
+        sim_ixs.extend_from_slice(ixs);
+        let tx = Transaction::new_with_payer(&sim_ixs, Some(&payer));
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            replace_recent_blockhash: true,
+            commitment: Some(self.rpc_client.commitment()),
+            ..Default::default()
+        };
+        let fallback = default_cu_limit(&sim_ixs);
+        match self.rpc_client.simulate_transaction_with_config(&tx, config).await {
+            Ok(response) => {
+                let result = response.value;
+                match (result.err, result.units_consumed) {
+                    (Some(err), _) if is_permanent_transaction_error(&err) => {
+                        let logs = result.logs.unwrap_or_default();
+                        Err(SendError::from_transaction_error(err, &sim_ixs, logs))
+                    }
+                    (None, Some(units)) if units > 0 => {
+                        let limit = units.saturating_mul(100 + CU_MARGIN_PERCENT) / 100;
+                        // Leave room for the tip transfer, which is not simulated
+                        Ok(((limit as u32) + CU_LIMIT_BUILTIN).min(CU_LIMIT_DEFAULT))
+                    }
+                    _ => Ok(fallback),
+                }
+            }
+            Err(_) => Ok(fallback),
+        }
+    }
+
+    /// Returns the priority fee to pay, in microlamports. With dynamic fees enabled this is the
+    /// 75th percentile of recent fees paid to write the same accounts.
+    async fn priority_fee(&self, ixs: &[Instruction]) -> u64 {
+        if !self.dynamic_fee {
+            return PRIORITY_FEE;
+        }
+        let accounts: Vec<Pubkey> = ixs
+            .iter()
+            .flat_map(|ix| ix.accounts.iter())
+            .filter(|account| account.is_writable)
+            .map(|account| account.pubkey)
+            .collect();
+        match self.rpc_client.get_recent_prioritization_fees(&accounts).await {
+            Ok(fees) => {
+                let mut fees: Vec<u64> = fees
+                    .into_iter()
+                    .map(|fee| fee.prioritization_fee)
+                    .collect();
+                fees.sort_unstable();
+                fees
+                    .get((fees.len() * 3) / 4)
+                    .copied()
+                    .unwrap_or(PRIORITY_FEE)
+                    .clamp(PRIORITY_FEE, MAX_PRIORITY_FEE)
+            }
+            Err(_) => PRIORITY_FEE,
+        }
+    }
 
-        let to = Pubkey::from_str(TIPS.choose(&mut rand::thread_rng()).unwrap());
+    fn get_tip_transfer_ix(&self, from: Pubkey, amount: u64) -> ClientResult<Instruction> {
+        let to = Pubkey::from_str(JITO_TIP_ACCOUNTS.choose(&mut rand::thread_rng()).unwrap());
 
         Ok(transfer(&from, &to.unwrap(), amount))
     }
 }
+
+/// Returns true if resubmitting the transaction cannot succeed, for example because a program
+/// rejected it during preflight.
+fn is_permanent_error(err: &ClientError) -> bool {
+    err.get_transaction_error().map_or(false, |err| is_permanent_transaction_error(&err))
+}
+
+/// Returns true for transaction errors that resubmitting cannot fix. Errors caused by an expired
+/// blockhash, account or block contention, or cluster state are transient and worth retrying.
+fn is_permanent_transaction_error(err: &TransactionError) -> bool {
+    !matches!(
+        err,
+        TransactionError::BlockhashNotFound |
+            TransactionError::AlreadyProcessed |
+            TransactionError::AccountInUse |
+            TransactionError::AccountBorrowOutstanding |
+            TransactionError::ClusterMaintenance |
+            TransactionError::WouldExceedMaxBlockCostLimit |
+            TransactionError::WouldExceedMaxAccountCostLimit |
+            TransactionError::WouldExceedMaxVoteCostLimit |
+            TransactionError::WouldExceedAccountDataBlockLimit |
+            TransactionError::WouldExceedAccountDataTotalLimit |
+            TransactionError::ResanitizationNeeded
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::instruction::InstructionError;
+
+    #[test]
+    fn retries_transient_transaction_errors() {
+        for err in [
+            TransactionError::BlockhashNotFound,
+            TransactionError::AlreadyProcessed,
+            TransactionError::AccountInUse,
+            TransactionError::ClusterMaintenance,
+            TransactionError::WouldExceedMaxBlockCostLimit,
+            TransactionError::WouldExceedMaxAccountCostLimit,
+        ] {
+            assert!(!is_permanent_transaction_error(&err), "{:?}", err);
+        }
+    }
+
+    #[test]
+    fn gives_up_on_permanent_transaction_errors() {
+        for err in [
+            TransactionError::InstructionError(0, InstructionError::Custom(5)),
+            TransactionError::InstructionError(1, InstructionError::InvalidAccountData),
+            TransactionError::InsufficientFundsForFee,
+            TransactionError::AccountNotFound,
+            TransactionError::SignatureFailure,
+        ] {
+            assert!(is_permanent_transaction_error(&err), "{:?}", err);
+        }
+    }
+}
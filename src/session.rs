@@ -0,0 +1,121 @@
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::{
+    telemetry::Telemetry,
+    utils::{amount_u64_to_f64, amount_u64_to_string, format_age},
+    watch::amount_change,
+};
+
+/// What the mining loop did since it started.
+pub struct Session {
+    started: Instant,
+    rounds: u64,
+    hashes: u64,
+    hashing_time: Duration,
+    best_difficulty: u32,
+    landed: u64,
+    failed: u64,
+    first_stake: Option<u64>,
+    last_stake: u64,
+}
+
+/// The stats of a session, as reported over the control socket.
+#[derive(Debug, Serialize)]
+pub struct SessionStats {
+    /// The number of seconds since the session started.
+    pub uptime: u64,
+    pub rounds: u64,
+    pub landed: u64,
+    pub failed: u64,
+    pub hashes: u64,
+    /// The average number of hashes checked per second while hashing.
+    pub hashrate: f64,
+    pub best_difficulty: u32,
+    /// The stake of the proof, in ORE.
+    pub stake: f64,
+    /// The change in stake since the session started, in ORE.
+    pub stake_change: f64,
+}
+
+impl Session {
+    /// Starts a session now.
+    pub fn start() -> Self {
+        Self {
+            started: Instant::now(),
+            rounds: 0,
+            hashes: 0,
+            hashing_time: Duration::ZERO,
+            best_difficulty: 0,
+            landed: 0,
+            failed: 0,
+            first_stake: None,
+            last_stake: 0,
+        }
+    }
+
+    /// Records the stake of the proof at the start of a round.
+    pub fn record_stake(&mut self, stake: u64) {
+        self.first_stake.get_or_insert(stake);
+        self.last_stake = stake;
+    }
+
+    /// Records the hashing stats of a finished round.
+    pub fn record_round(&mut self, telemetry: &Telemetry) {
+        self.rounds += 1;
+        self.hashes += telemetry.hashes();
+        self.hashing_time += telemetry.elapsed();
+        self.best_difficulty = self.best_difficulty.max(telemetry.best_difficulty());
+    }
+
+    /// Records whether a submission landed.
+    pub fn record_submission(&mut self, landed: bool) {
+        if landed {
+            self.landed += 1;
+        } else {
+            self.failed += 1;
+        }
+    }
+
+    /// Returns the stats of the session so far.
+    pub fn stats(&self) -> SessionStats {
+        let first_stake = self.first_stake.unwrap_or(0);
+        SessionStats {
+            uptime: self.started.elapsed().as_secs(),
+            rounds: self.rounds,
+            landed: self.landed,
+            failed: self.failed,
+            hashes: self.hashes,
+            hashrate: self.hashrate(),
+            best_difficulty: self.best_difficulty,
+            stake: amount_u64_to_f64(self.last_stake),
+            stake_change: amount_u64_to_f64(self.last_stake) - amount_u64_to_f64(first_stake),
+        }
+    }
+
+    /// Describes the session, one stat per line.
+    pub fn summary(&self) -> String {
+        format!(
+            "Duration: {}\n\
+             Rounds: {}\n\
+             Submissions: {} landed, {} failed\n\
+             Hashes: {} ({:.0} H/s)\n\
+             Best difficulty: {}\n\
+             Stake: {} ORE ({} ORE)",
+            format_age(self.started.elapsed().as_secs() as i64),
+            self.rounds,
+            self.landed,
+            self.failed,
+            self.hashes,
+            self.hashrate(),
+            self.best_difficulty,
+            amount_u64_to_string(self.last_stake),
+            amount_change(self.first_stake.unwrap_or(0), self.last_stake)
+        )
+    }
+
+    fn hashrate(&self) -> f64 {
+        self.hashes as f64 / self.hashing_time.as_secs_f64().max(f64::EPSILON)
+    }
+}
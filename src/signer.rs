@@ -0,0 +1,315 @@
+use aes_gcm_siv::{
+    aead::{Aead, NewAead},
+    Aes256GcmSiv, Key, Nonce,
+};
+use clap_v2::ArgMatches;
+use colored::*;
+use hmac::Hmac;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use solana_clap_utils::keypair::signer_from_path;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    signer::SignerError,
+};
+use tokio::runtime::RuntimeFlavor;
+
+use crate::{
+    args::{KeypairArgs, KeypairCommand},
+    signer_server::{ErrorResponse, PubkeyResponse, SignRequest, SignResponse},
+    utils::read_keypair,
+    Miner,
+};
+
+/// The environment variable read for the password of encrypted keypair files, so unattended
+/// miners do not need a terminal.
+const PASSWORD_ENV: &str = "ORE_KEYPAIR_PASSWORD";
+
+/// The PBKDF2 iteration count for new encrypted keypair files.
+const KDF_ITERATIONS: u32 = 600_000;
+
+/// The fewest PBKDF2 iterations accepted when decrypting, so a file cannot weaken the key.
+const MIN_KDF_ITERATIONS: u32 = 100_000;
+
+/// The most PBKDF2 iterations accepted when decrypting, so a file cannot stall startup for hours.
+const MAX_KDF_ITERATIONS: u32 = 10_000_000;
+
+const KDF_NAME: &str = "pbkdf2-sha256";
+
+/// A keypair file encrypted with a password. Fields are base58 encoded.
+#[derive(Debug, Deserialize, Serialize)]
+struct EncryptedKeypair {
+    kdf: String,
+    iterations: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Resolves a signer from a keypair source. Supports keypair files, password-encrypted keypair
+/// files, inline base58 or JSON keypairs, and the Solana signer URIs `file://`, `prompt://`,
+/// `usb://ledger` and `stdin`.
+pub fn resolve_signer(source: &str, keypair_name: &str) -> Result<Box<dyn Signer>, String> {
+    let source = source.trim();
+
+    // Inline keypairs, usually passed through environment variables
+    if is_inline_keypair(source) {
+        return read_keypair(source).map(|keypair| Box::new(keypair) as Box<dyn Signer>);
+    }
+
+    // Password-encrypted keypair files
+    if let Some(encrypted) = read_encrypted_keypair_file(source) {
+        let password = read_password(&format!("[{}] password: ", keypair_name), false)?;
+        return decrypt_keypair(&encrypted, &password)
+            .map(|keypair| Box::new(keypair) as Box<dyn Signer>);
+    }
+
+    // Keypair files and signer URIs
+    if !source.contains("://") && source.ne("stdin") && !std::path::Path::new(source).exists() {
+        return Err(format!(
+            "No keypair found at {}. Run `solana-keygen new` to create one, or pass --keypair.",
+            source
+        ));
+    }
+    let mut wallet_manager = None;
+    signer_from_path(
+        &ArgMatches::default(),
+        source,
+        keypair_name,
+        &mut wallet_manager,
+    )
+    .map_err(|err| {
+        if source.starts_with("usb://") && !cfg!(feature = "ledger") {
+            format!(
+                "Hardware wallets require ore-cli to be built with `--features ledger`: {}",
+                err
+            )
+        } else {
+            err.to_string()
+        }
+    })
+}
+
+/// A signer that forwards messages to an `ore signer-server` over HTTP, so the authority
+/// keypair does not need to live on the mining rig.
+pub struct RemoteSigner {
+    url: String,
+    token: Option<String>,
+    pubkey: Pubkey,
+    client: reqwest::Client,
+}
+
+impl RemoteSigner {
+    /// Connects to a signer-server and fetches the public key it signs for.
+    pub fn connect(url: &str, token: Option<String>) -> Result<Self, String> {
+        let mut signer = Self {
+            url: url.trim_end_matches('/').to_string(),
+            token,
+            pubkey: Pubkey::default(),
+            client: reqwest::Client::new(),
+        };
+        let response: PubkeyResponse =
+            block_on(signer.request(signer.client.get(signer.endpoint("pubkey"))))
+                .and_then(|response| response)
+                .map_err(|err| format!("Failed to reach signer at {}: {}", url, err))?;
+        signer.pubkey = response
+            .pubkey
+            .parse()
+            .map_err(|_| format!("Signer at {} returned an invalid pubkey", url))?;
+        Ok(signer)
+    }
+
+    fn endpoint(&self, path: &str) -> String {
+        format!("{}/{}", self.url, path)
+    }
+
+    async fn request<T: serde::de::DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T, String> {
+        let request = match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        };
+        let response = request.send().await.map_err(|err| err.to_string())?;
+        if !response.status().is_success() {
+            let status = response.status();
+            return Err(match response.json::<ErrorResponse>().await {
+                Ok(body) => body.error,
+                Err(_) => status.to_string(),
+            });
+        }
+        response.json().await.map_err(|err| err.to_string())
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.pubkey)
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        let body = SignRequest {
+            message: bs58::encode(message).into_string(),
+        };
+        let response: SignResponse =
+            block_on(self.request(self.client.post(self.endpoint("sign")).json(&body)))
+                .and_then(|response| response)
+                .map_err(SignerError::Custom)?;
+        let signature: Signature = response
+            .signature
+            .parse()
+            .map_err(|_| SignerError::Custom("Invalid signature from signer".to_string()))?;
+        if !signature.verify(self.pubkey.as_ref(), message) {
+            return Err(SignerError::Custom(
+                "Signature from signer does not verify".to_string(),
+            ));
+        }
+        Ok(signature)
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+/// Runs a future to completion from synchronous code inside the tokio runtime. The Solana
+/// `Signer` trait is synchronous, so this blocks the current worker with `block_in_place`, which
+/// needs the multi-threaded runtime `main` starts. Any other runtime is an error rather than a
+/// panic.
+fn block_on<F: std::future::Future>(future: F) -> Result<F::Output, String> {
+    let handle = tokio::runtime::Handle::try_current()
+        .map_err(|_| "The remote signer needs a tokio runtime".to_string())?;
+    if handle.runtime_flavor().ne(&RuntimeFlavor::MultiThread) {
+        return Err("The remote signer needs a multi-threaded tokio runtime".to_string());
+    }
+    Ok(tokio::task::block_in_place(|| handle.block_on(future)))
+}
+
+/// Describes a keypair source for display without revealing inline keys, which are shown by
+/// their public key instead.
+pub fn describe_signer_source(source: &str) -> String {
+    let source = source.trim();
+    if !is_inline_keypair(source) {
+        return source.to_string();
+    }
+    match read_keypair(source) {
+        Ok(keypair) => format!("<inline keypair: {}>", keypair.pubkey()),
+        Err(_) => "<inline keypair>".to_string(),
+    }
+}
+
+fn is_inline_keypair(source: &str) -> bool {
+    source.starts_with('[')
+        || bs58::decode(source)
+            .into_vec()
+            .map_or(false, |bytes| bytes.len().eq(&64))
+}
+
+fn read_encrypted_keypair_file(path: &str) -> Option<EncryptedKeypair> {
+    let path = path.strip_prefix("file://").unwrap_or(path);
+    let data = std::fs::read_to_string(path).ok()?;
+    if !data.trim_start().starts_with('{') {
+        return None;
+    }
+    serde_json::from_str(&data).ok()
+}
+
+fn read_password(prompt: &str, confirm: bool) -> Result<String, String> {
+    if let Ok(password) = std::env::var(PASSWORD_ENV) {
+        return Ok(password);
+    }
+    let password = rpassword::prompt_password(prompt).map_err(|err| err.to_string())?;
+    if confirm {
+        let confirmation =
+            rpassword::prompt_password("Confirm password: ").map_err(|err| err.to_string())?;
+        if password.ne(&confirmation) {
+            return Err("Passwords do not match".to_string());
+        }
+    }
+    Ok(password)
+}
+
+fn derive_key(password: &str, salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(password.as_bytes(), salt, iterations, &mut key);
+    key
+}
+
+fn encrypt_keypair(keypair: &Keypair, password: &str) -> EncryptedKeypair {
+    let mut rng = rand::thread_rng();
+    let salt: [u8; 16] = rng.gen();
+    let nonce: [u8; 12] = rng.gen();
+    let key = derive_key(password, &salt, KDF_ITERATIONS);
+    let cipher = Aes256GcmSiv::new(Key::from_slice(&key));
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), keypair.to_bytes().as_slice())
+        .expect("Failed to encrypt keypair");
+    EncryptedKeypair {
+        kdf: KDF_NAME.to_string(),
+        iterations: KDF_ITERATIONS,
+        salt: bs58::encode(salt).into_string(),
+        nonce: bs58::encode(nonce).into_string(),
+        ciphertext: bs58::encode(ciphertext).into_string(),
+    }
+}
+
+fn decrypt_keypair(encrypted: &EncryptedKeypair, password: &str) -> Result<Keypair, String> {
+    if encrypted.kdf.ne(KDF_NAME) {
+        return Err(format!("Unsupported keypair encryption: {}", encrypted.kdf));
+    }
+    if !(MIN_KDF_ITERATIONS..=MAX_KDF_ITERATIONS).contains(&encrypted.iterations) {
+        return Err(format!(
+            "Invalid encrypted keypair file: {} iterations is outside {}..={}",
+            encrypted.iterations, MIN_KDF_ITERATIONS, MAX_KDF_ITERATIONS
+        ));
+    }
+    let decode = |field: &str| {
+        bs58::decode(field)
+            .into_vec()
+            .map_err(|_| "Invalid encrypted keypair file".to_string())
+    };
+    let salt = decode(&encrypted.salt)?;
+    let nonce = decode(&encrypted.nonce)?;
+    let ciphertext = decode(&encrypted.ciphertext)?;
+    if nonce.len().ne(&12) {
+        return Err("Invalid encrypted keypair file".to_string());
+    }
+    let key = derive_key(password, &salt, encrypted.iterations);
+    let cipher = Aes256GcmSiv::new(Key::from_slice(&key));
+    let bytes = cipher
+        .decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| "Wrong password for encrypted keypair".to_string())?;
+    Keypair::from_bytes(&bytes).map_err(|err| format!("Invalid keypair: {}", err))
+}
+
+impl Miner {
+    pub async fn keypair(&self, args: KeypairArgs) {
+        match args.command {
+            KeypairCommand::Encrypt(args) => {
+                let Some(source) = args.keypair.or(self.keypair_filepath.clone()) else {
+                    eprintln!("{} No keypair provided", "ERROR".bold().red());
+                    std::process::exit(1);
+                };
+                let result = read_keypair(&source)
+                    .and_then(|keypair| {
+                        let password = read_password("New password: ", true)?;
+                        Ok(encrypt_keypair(&keypair, &password))
+                    })
+                    .and_then(|encrypted| {
+                        let data = serde_json::to_string_pretty(&encrypted).unwrap();
+                        std::fs::write(&args.out, data).map_err(|err| err.to_string())
+                    });
+                match result {
+                    Ok(()) => println!("Wrote encrypted keypair to {}", args.out),
+                    Err(err) => {
+                        eprintln!("{} {}", "ERROR".bold().red(), err);
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+    }
+}
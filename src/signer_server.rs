@@ -0,0 +1,558 @@
+use std::{str::FromStr, time::Duration};
+
+use colored::*;
+use ore_api::{
+    consts::{MINT_ADDRESS, NOOP_PROGRAM_ID},
+    instruction::OreInstruction,
+};
+use serde::{Deserialize, Serialize};
+use solana_program::{
+    instruction::CompiledInstruction, message::Message, pubkey::Pubkey, system_instruction,
+    system_program,
+};
+use solana_sdk::{compute_budget, signature::Signer};
+use spl_associated_token_account::get_associated_token_address;
+use subtle::ConstantTimeEq;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{
+    args::SignerServerArgs,
+    cu_limits::CU_LIMIT_DEFAULT,
+    mine::{MINE_DISCRIMINATOR, POOL_PROGRAM_ID},
+    send_and_confirm::JITO_TIP_ACCOUNTS,
+    Miner,
+};
+
+/// The largest request the server reads, in bytes. Transactions are at most 1232 bytes.
+const MAX_REQUEST_SIZE: u64 = 16 * 1024;
+
+/// How long a client may take to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The response body of `GET /pubkey`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PubkeyResponse {
+    pub pubkey: String,
+}
+
+/// The request body of `POST /sign`. The message is a base58 encoded legacy transaction message.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SignRequest {
+    pub message: String,
+}
+
+/// The response body of `POST /sign`.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SignResponse {
+    pub signature: String,
+}
+
+/// The response body of failed requests.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+/// The instructions the signer-server is willing to sign.
+#[derive(Debug)]
+pub struct SigningPolicy {
+    pub max_priority_fee: u64,
+    pub max_tip: u64,
+    pub beneficiaries: Vec<Pubkey>,
+}
+
+/// The compute budget and tips requested across a message, checked once every instruction has
+/// been seen.
+#[derive(Default)]
+struct MessageCosts {
+    unit_limit: Option<u32>,
+    unit_price: Option<u64>,
+    tips: u64,
+    tip_count: usize,
+}
+
+impl SigningPolicy {
+    /// Checks that every instruction in the message is allowed, and that the priority fee and
+    /// Jito tips of the message as a whole are within the limits.
+    pub fn check(&self, message: &Message) -> Result<(), String> {
+        let mut costs = MessageCosts::default();
+        for ix in &message.instructions {
+            let program_id = message
+                .account_keys
+                .get(ix.program_id_index as usize)
+                .ok_or("Invalid program id index")?;
+            if program_id.eq(&compute_budget::id()) {
+                check_compute_budget(ix, &mut costs)?;
+            } else if program_id.eq(&ore_api::ID) {
+                self.check_ore(message, ix)?;
+            } else if program_id.eq(&POOL_PROGRAM_ID) {
+                if !ix.data.starts_with(&MINE_DISCRIMINATOR) {
+                    return Err("Only mine instructions are allowed for the pool program".into());
+                }
+            } else if program_id.eq(&NOOP_PROGRAM_ID) {
+                // Proof authentication
+            } else if program_id.eq(&system_program::id()) {
+                check_system(message, ix, &mut costs)?;
+            } else {
+                return Err(format!("Program {} is not allowed", program_id));
+            }
+        }
+
+        // The priority fee is the unit price times the unit limit, so both are capped
+        if let Some(price) = costs.unit_price {
+            if price > self.max_priority_fee {
+                return Err(format!(
+                    "Priority fee {} exceeds the limit of {}",
+                    price, self.max_priority_fee
+                ));
+            }
+            let Some(limit) = costs.unit_limit else {
+                return Err("Compute unit price without a compute unit limit".into());
+            };
+            if limit > CU_LIMIT_DEFAULT {
+                return Err(format!(
+                    "Compute unit limit {} exceeds the limit of {}",
+                    limit, CU_LIMIT_DEFAULT
+                ));
+            }
+        }
+        if costs.tip_count > 1 {
+            return Err("Only one Jito tip is allowed".into());
+        }
+        if costs.tips > self.max_tip {
+            return Err(format!(
+                "Jito tip {} exceeds the limit of {}",
+                costs.tips, self.max_tip
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_ore(&self, message: &Message, ix: &CompiledInstruction) -> Result<(), String> {
+        match ix.data.first().copied() {
+            Some(d) if d.eq(&(OreInstruction::Mine as u8)) => Ok(()),
+            Some(d) if d.eq(&(OreInstruction::Reset as u8)) => Ok(()),
+            Some(d) if d.eq(&(OreInstruction::Claim as u8)) => {
+                let beneficiary = account(message, ix, 1)?;
+                if !self.beneficiaries.contains(beneficiary) {
+                    return Err(format!("Beneficiary {} is not allowed", beneficiary));
+                }
+                Ok(())
+            }
+            _ => Err(
+                "Only mine, reset and claim instructions are allowed for the ORE program".into(),
+            ),
+        }
+    }
+}
+
+/// Records a compute unit limit or price, rejecting any other compute budget instruction and
+/// repeated ones.
+fn check_compute_budget(ix: &CompiledInstruction, costs: &mut MessageCosts) -> Result<(), String> {
+    match ix.data.first() {
+        // SetComputeUnitLimit
+        Some(2) => {
+            let limit = ix
+                .data
+                .get(1..5)
+                .and_then(|bytes| bytes.try_into().ok())
+                .map(u32::from_le_bytes)
+                .ok_or("Invalid compute unit limit")?;
+            if costs.unit_limit.replace(limit).is_some() {
+                return Err("Duplicate compute unit limit".into());
+            }
+            Ok(())
+        }
+        // SetComputeUnitPrice
+        Some(3) => {
+            let price = ix
+                .data
+                .get(1..9)
+                .and_then(|bytes| bytes.try_into().ok())
+                .map(u64::from_le_bytes)
+                .ok_or("Invalid compute unit price")?;
+            if costs.unit_price.replace(price).is_some() {
+                return Err("Duplicate compute unit price".into());
+            }
+            Ok(())
+        }
+        _ => Err("Compute budget instruction is not allowed".into()),
+    }
+}
+
+/// Allows nonce advances and records Jito tips.
+fn check_system(
+    message: &Message,
+    ix: &CompiledInstruction,
+    costs: &mut MessageCosts,
+) -> Result<(), String> {
+    let lamports = match bincode::deserialize(&ix.data) {
+        Ok(system_instruction::SystemInstruction::AdvanceNonceAccount) => return Ok(()),
+        Ok(system_instruction::SystemInstruction::Transfer { lamports }) => lamports,
+        _ => {
+            return Err(
+                "Only Jito tips and nonce advances are allowed for the system program".into(),
+            )
+        }
+    };
+    let to = account(message, ix, 1)?.to_string();
+    if !JITO_TIP_ACCOUNTS.contains(&to.as_str()) {
+        return Err(format!("Transfer to {} is not a Jito tip", to));
+    }
+    costs.tips = costs.tips.saturating_add(lamports);
+    costs.tip_count += 1;
+    Ok(())
+}
+
+fn account<'a>(
+    message: &'a Message,
+    ix: &CompiledInstruction,
+    position: usize,
+) -> Result<&'a Pubkey, String> {
+    ix.accounts
+        .get(position)
+        .and_then(|index| message.account_keys.get(*index as usize))
+        .ok_or_else(|| "Invalid account index".to_string())
+}
+
+impl Miner {
+    pub async fn signer_server(&self, args: SignerServerArgs) {
+        if self.signer_url.is_some() {
+            eprintln!(
+                "{} The signer-server must hold a local keypair. Remove --signer-url.",
+                "ERROR".bold().red()
+            );
+            std::process::exit(1);
+        }
+        let beneficiaries = args
+            .beneficiary
+            .iter()
+            .map(|address| {
+                Pubkey::from_str(address).unwrap_or_else(|_| {
+                    eprintln!("{} Invalid address: {}", "ERROR".bold().red(), address);
+                    std::process::exit(1);
+                })
+            })
+            .collect::<Vec<Pubkey>>();
+        let signer = self.signer();
+        // Without explicit beneficiaries, claims may only pay out to the signer's own token
+        // account, so a client cannot redirect the authority's rewards
+        let beneficiaries = if beneficiaries.is_empty() {
+            vec![get_associated_token_address(
+                &signer.pubkey(),
+                &MINT_ADDRESS,
+            )]
+        } else {
+            beneficiaries
+        };
+        let policy = SigningPolicy {
+            max_priority_fee: args.max_priority_fee,
+            max_tip: args.max_tip,
+            beneficiaries,
+        };
+        let listener = TcpListener::bind(&args.listen).await.unwrap_or_else(|err| {
+            eprintln!(
+                "{} Failed to listen on {}: {}",
+                "ERROR".bold().red(),
+                args.listen,
+                err
+            );
+            std::process::exit(1);
+        });
+        if self.signer_token.is_none() {
+            let loopback = listener
+                .local_addr()
+                .map_or(false, |addr| addr.ip().is_loopback());
+            if !loopback {
+                eprintln!(
+                    "{} Refusing to listen on {} without a --signer-token.",
+                    "ERROR".bold().red(),
+                    args.listen
+                );
+                std::process::exit(1);
+            }
+            println!(
+                "{} No --signer-token set. Any local process may request signatures.",
+                "WARNING".bold().yellow()
+            );
+        }
+        println!(
+            "Signing for {} on {}",
+            signer.pubkey().to_string().bold(),
+            args.listen
+        );
+
+        // Requests are handled one at a time, since hardware wallets cannot sign concurrently.
+        loop {
+            let Ok((stream, peer)) = listener.accept().await else {
+                continue;
+            };
+            if let Err(err) = self.handle_sign_request(stream, signer, &policy).await {
+                println!("{} {}: {}", "WARNING".bold().yellow(), peer, err);
+            }
+        }
+    }
+
+    async fn handle_sign_request(
+        &self,
+        stream: TcpStream,
+        signer: &dyn Signer,
+        policy: &SigningPolicy,
+    ) -> std::io::Result<()> {
+        let mut reader = BufReader::new(stream.take(MAX_REQUEST_SIZE));
+        let request = tokio::time::timeout(REQUEST_TIMEOUT, read_request(&mut reader))
+            .await
+            .map_err(|_| {
+                std::io::Error::new(std::io::ErrorKind::TimedOut, "Request timed out")
+            })??;
+        let (status, body) = self.route(request, signer, policy);
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        let mut stream = reader.into_inner().into_inner();
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await
+    }
+
+    fn route(
+        &self,
+        request: HttpRequest,
+        signer: &dyn Signer,
+        policy: &SigningPolicy,
+    ) -> (&'static str, String) {
+        if let Some(token) = &self.signer_token {
+            // Compare in constant time, so the token cannot be guessed byte by byte
+            let expected = format!("Bearer {}", token);
+            let authorized = request
+                .authorization
+                .as_deref()
+                .map_or(false, |authorization| {
+                    authorization.as_bytes().ct_eq(expected.as_bytes()).into()
+                });
+            if !authorized {
+                return error_response("401 Unauthorized", "Invalid signer token");
+            }
+        }
+        match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/pubkey") => {
+                let response = PubkeyResponse {
+                    pubkey: signer.pubkey().to_string(),
+                };
+                ("200 OK", serde_json::to_string(&response).unwrap())
+            }
+            ("POST", "/sign") => {
+                let message = serde_json::from_slice::<SignRequest>(&request.body)
+                    .ok()
+                    .and_then(|request| bs58::decode(request.message).into_vec().ok());
+                let Some(message_bytes) = message else {
+                    return error_response("400 Bad Request", "Invalid sign request");
+                };
+                let Ok(message) = bincode::deserialize::<Message>(&message_bytes) else {
+                    return error_response("400 Bad Request", "Invalid transaction message");
+                };
+                if let Err(err) = policy.check(&message) {
+                    println!(
+                        "{} Rejected transaction: {}",
+                        "WARNING".bold().yellow(),
+                        err
+                    );
+                    return error_response("403 Forbidden", &err);
+                }
+                match signer.try_sign_message(&message_bytes) {
+                    Ok(signature) => {
+                        println!("Signed transaction {}", signature);
+                        let response = SignResponse {
+                            signature: signature.to_string(),
+                        };
+                        ("200 OK", serde_json::to_string(&response).unwrap())
+                    }
+                    Err(err) => error_response("500 Internal Server Error", &err.to_string()),
+                }
+            }
+            _ => error_response("404 Not Found", "Not found"),
+        }
+    }
+}
+
+struct HttpRequest {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+    body: Vec<u8>,
+}
+
+async fn read_request<R>(reader: &mut R) -> std::io::Result<HttpRequest>
+where
+    R: AsyncBufReadExt + Unpin,
+{
+    let mut line = String::new();
+    reader.read_line(&mut line).await?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    // Headers
+    let mut content_length = 0;
+    let mut authorization = None;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await?.eq(&0) {
+            break;
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            match name.trim().to_ascii_lowercase().as_str() {
+                "content-length" => content_length = value.trim().parse().unwrap_or(0),
+                "authorization" => authorization = Some(value.trim().to_string()),
+                _ => {}
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length.min(MAX_REQUEST_SIZE as usize)];
+    reader.read_exact(&mut body).await?;
+    Ok(HttpRequest {
+        method,
+        path,
+        authorization,
+        body,
+    })
+}
+
+fn error_response(status: &'static str, error: &str) -> (&'static str, String) {
+    let response = ErrorResponse {
+        error: error.to_string(),
+    };
+    (status, serde_json::to_string(&response).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use drillx::Solution;
+    use ore_api::consts::BUS_ADDRESSES;
+    use solana_program::instruction::Instruction;
+    use solana_sdk::compute_budget::ComputeBudgetInstruction;
+
+    use super::*;
+
+    const AUTHORITY: Pubkey = Pubkey::new_from_array([1; 32]);
+
+    fn policy() -> SigningPolicy {
+        SigningPolicy {
+            max_priority_fee: 1_000,
+            max_tip: 10_000,
+            beneficiaries: vec![get_associated_token_address(&AUTHORITY, &MINT_ADDRESS)],
+        }
+    }
+
+    fn check(ixs: &[Instruction]) -> Result<(), String> {
+        policy().check(&Message::new(ixs, Some(&AUTHORITY)))
+    }
+
+    fn fee(limit: u32, price: u64) -> [Instruction; 2] {
+        [
+            ComputeBudgetInstruction::set_compute_unit_limit(limit),
+            ComputeBudgetInstruction::set_compute_unit_price(price),
+        ]
+    }
+
+    fn tip(lamports: u64) -> Instruction {
+        let tip_account = Pubkey::from_str(JITO_TIP_ACCOUNTS[0]).unwrap();
+        system_instruction::transfer(&AUTHORITY, &tip_account, lamports)
+    }
+
+    fn mine() -> Instruction {
+        ore_api::instruction::mine(
+            AUTHORITY,
+            AUTHORITY,
+            BUS_ADDRESSES[0],
+            Solution::new([0; 16], [0; 8]),
+        )
+    }
+
+    fn claim(beneficiary: Pubkey) -> Instruction {
+        ore_api::instruction::claim(AUTHORITY, beneficiary, 1)
+    }
+
+    #[test]
+    fn allows_mine_with_fee_and_tip() {
+        let [limit, price] = fee(10_000, 1_000);
+        assert_eq!(check(&[limit, price, tip(10_000), mine()]), Ok(()));
+    }
+
+    #[test]
+    fn allows_claim_to_the_signers_token_account() {
+        let beneficiary = get_associated_token_address(&AUTHORITY, &MINT_ADDRESS);
+        assert_eq!(check(&[claim(beneficiary)]), Ok(()));
+    }
+
+    #[test]
+    fn rejects_claim_to_another_account() {
+        let beneficiary = Pubkey::new_unique();
+        assert!(check(&[claim(beneficiary)]).is_err());
+    }
+
+    #[test]
+    fn rejects_tip_over_the_cap() {
+        assert!(check(&[tip(10_001), mine()]).is_err());
+    }
+
+    #[test]
+    fn rejects_stacked_tips() {
+        assert!(check(&[tip(6_000), tip(6_000), mine()]).is_err());
+        assert!(check(&[tip(1), tip(1), mine()]).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_program() {
+        let ix = Instruction::new_with_bytes(Pubkey::new_unique(), &[0], vec![]);
+        assert!(check(&[ix]).is_err());
+    }
+
+    #[test]
+    fn rejects_transfer_to_a_non_tip_account() {
+        let ix = system_instruction::transfer(&AUTHORITY, &Pubkey::new_unique(), 1);
+        assert!(check(&[ix, mine()]).is_err());
+    }
+
+    #[test]
+    fn rejects_priority_fee_over_the_cap() {
+        let [limit, price] = fee(10_000, 1_001);
+        assert!(check(&[limit, price, mine()]).is_err());
+    }
+
+    #[test]
+    fn rejects_compute_unit_limit_over_the_cap() {
+        let [limit, price] = fee(CU_LIMIT_DEFAULT + 1, 1_000);
+        assert!(check(&[limit, price, mine()]).is_err());
+    }
+
+    #[test]
+    fn rejects_price_without_limit() {
+        let [_, price] = fee(10_000, 1_000);
+        assert!(check(&[price, mine()]).is_err());
+    }
+
+    #[test]
+    fn rejects_duplicate_compute_budget_instructions() {
+        let [limit, price] = fee(10_000, 1_000);
+        assert!(check(&[limit.clone(), limit, price.clone(), mine()]).is_err());
+        let [limit, _] = fee(10_000, 1_000);
+        assert!(check(&[limit, price.clone(), price, mine()]).is_err());
+    }
+
+    #[test]
+    fn rejects_other_compute_budget_instructions() {
+        let ix = ComputeBudgetInstruction::request_heap_frame(64 * 1024);
+        assert!(check(&[ix, mine()]).is_err());
+    }
+}
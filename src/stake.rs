@@ -0,0 +1,49 @@
+use std::str::FromStr;
+
+use ore_api::consts::MINT_ADDRESS;
+use solana_program::pubkey::Pubkey;
+use spl_associated_token_account::get_associated_token_address;
+
+use crate::{
+    args::StakeArgs,
+    exit_with_error,
+    utils::{amount_f64_to_u64, amount_u64_to_string},
+    Miner,
+};
+
+impl Miner {
+    pub async fn stake(&self, args: StakeArgs) {
+        let signer = self.signer();
+        let pubkey = signer.pubkey();
+        let sender = match args.token_account {
+            Some(address) => Pubkey::from_str(&address).unwrap_or_else(|_| {
+                exit_with_error(&format!("Invalid token account: {}", address))
+            }),
+            None => get_associated_token_address(&pubkey, &MINT_ADDRESS),
+        };
+
+        // Staking the max needs the token balance, which is only known online
+        let amount = match args.amount {
+            Some(amount) => amount_f64_to_u64(amount),
+            None if args.offline.sign_only => {
+                exit_with_error("Pass the amount to stake when signing offline")
+            }
+            None => match self.rpc_client.get_token_account_balance(&sender).await {
+                Ok(balance) => balance.amount.parse().unwrap_or(0),
+                Err(err) => {
+                    exit_with_error(&format!("Failed to get token account {}: {}", sender, err))
+                }
+            },
+        };
+        if amount.eq(&0) {
+            println!("No ORE to stake");
+            return;
+        }
+
+        let ixs = vec![ore_api::instruction::stake(pubkey, sender, amount)];
+        if !args.offline.sign_only {
+            println!("Staking {} ORE", amount_u64_to_string(amount));
+        }
+        self.send_or_sign(ixs, &args.offline).await;
+    }
+}
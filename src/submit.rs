@@ -0,0 +1,36 @@
+use std::io::Read;
+
+use solana_sdk::transaction::Transaction;
+
+use crate::{args::SubmitArgs, exit_with_error, Miner};
+
+impl Miner {
+    pub async fn submit_signed(&self, args: SubmitArgs) {
+        // Read the transaction printed by --sign-only
+        let encoded = if args.file.eq("-") {
+            let mut encoded = String::new();
+            std::io::stdin()
+                .read_to_string(&mut encoded)
+                .unwrap_or_else(|err| exit_with_error(&format!("Failed to read stdin: {}", err)));
+            encoded
+        } else {
+            std::fs::read_to_string(&args.file).unwrap_or_else(|err| {
+                exit_with_error(&format!("Failed to read {}: {}", args.file, err))
+            })
+        };
+        let tx = bs58::decode(encoded.trim())
+            .into_vec()
+            .ok()
+            .and_then(|bytes| bincode::deserialize::<Transaction>(&bytes).ok())
+            .unwrap_or_else(|| {
+                exit_with_error("Invalid transaction: expected base58 from --sign-only")
+            });
+        if tx.verify().is_err() {
+            exit_with_error("Transaction is missing signatures or has invalid ones");
+        }
+
+        if self.send_signed(&tx).await.is_err() {
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    sync::atomic::{AtomicU32, AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use crate::difficulty::MAX_TRACKED_DIFFICULTY;
+
+/// Live hashing stats of one round. Each hashing thread adds batches of counts to its own
+/// counters with relaxed atomics, so reporting never slows hashing down.
+pub struct Telemetry {
+    workers: Vec<WorkerTelemetry>,
+    best_difficulty: AtomicU32,
+    started: Instant,
+    /// The length of the round in milliseconds once it finished, or 0 while it runs.
+    finished_after: AtomicU64,
+}
+
+/// The counters of one hashing thread, aligned to a cache line so threads on different cores
+/// never write to the same line.
+#[repr(align(64))]
+struct WorkerTelemetry {
+    core: usize,
+    hashes: AtomicU64,
+    best_difficulty: AtomicU32,
+    /// The number of hashes found at each difficulty.
+    difficulties: [AtomicU64; MAX_TRACKED_DIFFICULTY + 1],
+}
+
+/// The hashing stats of one core.
+pub struct CoreStats {
+    pub core: usize,
+    pub hashes: u64,
+    pub hashrate: f64,
+    pub best_difficulty: u32,
+}
+
+impl Telemetry {
+    /// Creates the stats of a round hashed by threads pinned to the given cores, in order.
+    pub fn new(cores: impl IntoIterator<Item = usize>) -> Self {
+        let workers = cores
+            .into_iter()
+            .map(|core| WorkerTelemetry {
+                core,
+                hashes: AtomicU64::new(0),
+                best_difficulty: AtomicU32::new(0),
+                difficulties: std::array::from_fn(|_| AtomicU64::new(0)),
+            })
+            .collect();
+        Self {
+            workers,
+            best_difficulty: AtomicU32::new(0),
+            started: Instant::now(),
+            finished_after: AtomicU64::new(0),
+        }
+    }
+
+    /// Adds a batch of hashes checked by the worker at the given index, counted by difficulty.
+    pub fn record(&self, worker: usize, difficulties: &[u64; MAX_TRACKED_DIFFICULTY + 1]) {
+        let Some(stats) = self.workers.get(worker) else {
+            return;
+        };
+        stats
+            .hashes
+            .fetch_add(difficulties.iter().sum(), Ordering::Relaxed);
+        for (counter, count) in stats.difficulties.iter().zip(difficulties) {
+            if count.gt(&0) {
+                counter.fetch_add(*count, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Records a new best difficulty found by the worker at the given index.
+    pub fn record_best(&self, worker: usize, difficulty: u32) {
+        let Some(stats) = self.workers.get(worker) else {
+            return;
+        };
+        stats
+            .best_difficulty
+            .fetch_max(difficulty, Ordering::Relaxed);
+        self.best_difficulty
+            .fetch_max(difficulty, Ordering::Relaxed);
+    }
+
+    /// Marks the round as finished, freezing the hashrate.
+    pub fn finish(&self) {
+        let elapsed = self.started.elapsed().as_millis().max(1) as u64;
+        self.finished_after.store(elapsed, Ordering::Relaxed);
+    }
+
+    /// The time spent hashing so far, or the length of the round once it finished.
+    pub fn elapsed(&self) -> Duration {
+        match self.finished_after.load(Ordering::Relaxed) {
+            0 => self.started.elapsed(),
+            millis => Duration::from_millis(millis),
+        }
+    }
+
+    /// The best difficulty found by any core.
+    pub fn best_difficulty(&self) -> u32 {
+        self.best_difficulty.load(Ordering::Relaxed)
+    }
+
+    /// The number of hashes checked across all cores.
+    pub fn hashes(&self) -> u64 {
+        self.workers
+            .iter()
+            .map(|worker| worker.hashes.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// The number of hashes checked per second across all cores.
+    pub fn hashrate(&self) -> f64 {
+        self.cores().iter().map(|core| core.hashrate).sum()
+    }
+
+    /// The stats of each core, by core id. Threads pinned to the same core add up.
+    pub fn cores(&self) -> Vec<CoreStats> {
+        let seconds = self.elapsed().as_secs_f64().max(f64::EPSILON);
+        let mut cores: Vec<CoreStats> = vec![];
+        for worker in &self.workers {
+            let hashes = worker.hashes.load(Ordering::Relaxed);
+            let best_difficulty = worker.best_difficulty.load(Ordering::Relaxed);
+            match cores.iter_mut().find(|stats| stats.core.eq(&worker.core)) {
+                Some(stats) => {
+                    stats.hashes += hashes;
+                    stats.best_difficulty = stats.best_difficulty.max(best_difficulty);
+                }
+                None => cores.push(CoreStats {
+                    core: worker.core,
+                    hashes,
+                    hashrate: 0.0,
+                    best_difficulty,
+                }),
+            }
+        }
+        cores.sort_unstable_by_key(|stats| stats.core);
+        for stats in cores.iter_mut() {
+            stats.hashrate = stats.hashes as f64 / seconds;
+        }
+        cores
+    }
+
+    /// The number of hashes found at each difficulty across all cores.
+    pub fn difficulties(&self) -> Vec<u64> {
+        (0..=MAX_TRACKED_DIFFICULTY)
+            .map(|difficulty| {
+                self.workers
+                    .iter()
+                    .map(|worker| worker.difficulties[difficulty].load(Ordering::Relaxed))
+                    .sum()
+            })
+            .collect()
+    }
+
+    /// Summarizes the hashrate of each core, e.g. `1200 H/s (core 0: 600, core 1: 600)`.
+    pub fn hashrate_summary(&self) -> String {
+        let cores: Vec<String> = self
+            .cores()
+            .iter()
+            .map(|stats| format!("core {}: {:.0}", stats.core, stats.hashrate))
+            .collect();
+        format!("{:.0} H/s ({})", self.hashrate(), cores.join(", "))
+    }
+
+    /// Summarizes the hashes found at the hardest `levels` difficulties reached, e.g.
+    /// `16: 40, 17: 21, 18: 9`.
+    pub fn difficulty_summary(&self, levels: usize) -> String {
+        let difficulties = self.difficulties();
+        let top = difficulties
+            .iter()
+            .rposition(|count| count.gt(&0))
+            .unwrap_or(0);
+        let levels: Vec<String> = difficulties
+            .iter()
+            .enumerate()
+            .take(top + 1)
+            .skip((top + 1).saturating_sub(levels))
+            .map(|(difficulty, count)| format!("{}: {}", difficulty, count))
+            .collect();
+        levels.join(", ")
+    }
+}
@@ -0,0 +1,51 @@
+use std::str::FromStr;
+
+use ore_api::consts::{MINT_ADDRESS, TOKEN_DECIMALS};
+use solana_program::pubkey::Pubkey;
+use spl_associated_token_account::get_associated_token_address;
+
+use crate::{
+    args::TransferArgs,
+    exit_with_error,
+    utils::{amount_f64_to_u64, amount_u64_to_string, create_token_account_ix},
+    Miner,
+};
+
+impl Miner {
+    pub async fn transfer(&self, args: TransferArgs) {
+        let signer = self.signer();
+        let pubkey = signer.pubkey();
+        let to = Pubkey::from_str(&args.to)
+            .unwrap_or_else(|_| exit_with_error(&format!("Invalid address: {}", args.to)));
+        let amount = amount_f64_to_u64(args.amount);
+        if amount.eq(&0) {
+            exit_with_error("Transfer amount must be greater than 0");
+        }
+
+        // Create the recipient token account if needed
+        let client = Some(self.rpc_client.as_ref()).filter(|_| !args.offline.sign_only);
+        let mut ixs = vec![];
+        ixs.extend(create_token_account_ix(client, self.fee_payer().pubkey(), to).await);
+        ixs.push(
+            spl_token::instruction::transfer_checked(
+                &spl_token::id(),
+                &get_associated_token_address(&pubkey, &MINT_ADDRESS),
+                &MINT_ADDRESS,
+                &get_associated_token_address(&to, &MINT_ADDRESS),
+                &pubkey,
+                &[],
+                amount,
+                TOKEN_DECIMALS,
+            )
+            .unwrap(),
+        );
+        if !args.offline.sign_only {
+            println!(
+                "Transferring {} ORE to {}",
+                amount_u64_to_string(amount),
+                to
+            );
+        }
+        self.send_or_sign(ixs, &args.offline).await;
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::{ Duration, Instant };
+
 use cached::proc_macro::cached;
 use ore_api::{
     consts::{ CONFIG_ADDRESS, MINT_ADDRESS, PROOF, TOKEN_DECIMALS, TREASURY_ADDRESS },
@@ -5,10 +7,21 @@ use ore_api::{
 };
 use ore_utils::AccountDeserialize;
 use serde::Deserialize;
-use solana_client::nonblocking::rpc_client::RpcClient;
-use solana_program::{ pubkey::Pubkey, sysvar };
-use solana_sdk::clock::Clock;
-use spl_associated_token_account::get_associated_token_address;
+use solana_client::{ nonblocking::rpc_client::RpcClient, nonce_utils };
+use solana_program::{ instruction::Instruction, nonce::state::Data, pubkey::Pubkey, sysvar };
+use solana_sdk::{ clock::Clock, signature::{ read_keypair_file, Keypair } };
+use spl_associated_token_account::{
+    get_associated_token_address,
+    instruction::create_associated_token_account_idempotent,
+};
+
+use crate::control::MineControl;
+
+/// How often the proof is polled while waiting for a landed solution to show up.
+const PROOF_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long to wait for a landed solution to show up in the proof before mining on anyway.
+const PROOF_UPDATE_TIMEOUT: Duration = Duration::from_secs(60);
 
 pub async fn _get_treasury(client: &RpcClient) -> Treasury {
     let data = client
@@ -29,8 +42,26 @@ pub async fn get_proof_with_authority(client: &RpcClient, authority: Pubkey) ->
     get_proof(client, proof_address).await
 }
 
-pub async fn get_updated_proof_with_authority(client: &RpcClient, authority: Pubkey) -> Proof {
-    get_proof_with_authority(client, authority).await
+/// Fetches the proof, waiting until it records a hash newer than `last_hash_at`, so the next
+/// round does not mine a stale challenge. Gives up waiting after `PROOF_UPDATE_TIMEOUT` and
+/// returns the proof as is, or returns `None` once mining is paused or shutting down.
+pub async fn get_updated_proof_with_authority(
+    client: &RpcClient,
+    authority: Pubkey,
+    last_hash_at: i64,
+    control: &MineControl
+) -> Option<Proof> {
+    let deadline = Instant::now() + PROOF_UPDATE_TIMEOUT;
+    loop {
+        let proof = get_proof_with_authority(client, authority).await;
+        if proof.last_hash_at.gt(&last_hash_at) || Instant::now().ge(&deadline) {
+            return Some(proof);
+        }
+        if control.is_paused() || control.is_shutting_down() {
+            return None;
+        }
+        tokio::time::sleep(PROOF_POLL_INTERVAL).await;
+    }
 }
 
 pub async fn get_proof(client: &RpcClient, address: Pubkey) -> Proof {
@@ -53,6 +84,53 @@ pub fn amount_u64_to_f64(amount: u64) -> f64 {
     (amount as f64) / (10f64).powf(TOKEN_DECIMALS as f64)
 }
 
+pub fn amount_f64_to_u64(amount: f64) -> u64 {
+    (amount * (10f64).powf(TOKEN_DECIMALS as f64)) as u64
+}
+
+/// Returns the reward multiplier of a proof with the given stake, up to 2x at the top balance.
+pub fn calculate_multiplier(balance: u64, top_balance: u64) -> f64 {
+    1.0 + ((balance as f64) / (top_balance as f64)).min(1.0f64)
+}
+
+/// Formats a number of seconds as a short age, e.g. `3m 20s`.
+pub fn format_age(seconds: i64) -> String {
+    match seconds {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m {}s", s / 60, s % 60),
+        s => format!("{}h {}m", s / 3600, (s % 3600) / 60),
+    }
+}
+
+/// Fetches the state of a durable nonce account, including the nonce value to sign with.
+pub async fn get_nonce_data(client: &RpcClient, nonce: Pubkey) -> Result<Data, String> {
+    let account = nonce_utils::nonblocking
+        ::get_account_with_commitment(client, &nonce, client.commitment()).await
+        .map_err(|err| format!("Failed to get nonce account {}: {}", nonce, err))?;
+    nonce_utils
+        ::data_from_account(&account)
+        .map_err(|err| format!("Invalid nonce account {}: {}", nonce, err))
+}
+
+/// Returns an instruction creating the ORE token account of the wallet, or none if it already
+/// exists. Without a client the account is assumed missing. The instruction is a no-op if the
+/// account exists by the time it lands.
+pub async fn create_token_account_ix(
+    client: Option<&RpcClient>,
+    payer: Pubkey,
+    wallet: Pubkey
+) -> Option<Instruction> {
+    if let Some(client) = client {
+        let address = get_associated_token_address(&wallet, &MINT_ADDRESS);
+        if let Ok(Some(_)) = client.get_token_account(&address).await {
+            return None;
+        }
+    }
+    Some(
+        create_associated_token_account_idempotent(&payer, &wallet, &MINT_ADDRESS, &spl_token::id())
+    )
+}
+
 #[cached]
 pub fn proof_pubkey(authority: Pubkey) -> Pubkey {
     Pubkey::find_program_address(&[PROOF, authority.as_ref()], &ore_api::ID).0
@@ -63,6 +141,29 @@ pub fn treasury_tokens_pubkey() -> Pubkey {
     get_associated_token_address(&TREASURY_ADDRESS, &MINT_ADDRESS)
 }
 
+/// Reads a keypair from a file path, or from the keypair itself encoded as JSON bytes or base58.
+/// Inline keypairs let containers pass keys through environment variables. Errors never echo
+/// inline key material.
+pub fn read_keypair(source: &str) -> Result<Keypair, String> {
+    let source = source.trim();
+    if source.starts_with('[') {
+        let bytes: Vec<u8> = serde_json
+            ::from_str(source)
+            .map_err(|_| "Invalid keypair: malformed JSON bytes".to_string())?;
+        return Keypair::from_bytes(&bytes).map_err(|err| format!("Invalid keypair: {}", err));
+    }
+    let is_base58 = !source.is_empty() && bs58::decode(source).into_vec().is_ok();
+    if !is_base58 || std::path::Path::new(source).exists() {
+        return read_keypair_file(source).map_err(|_| format!("No keypair found at {}", source));
+    }
+    bs58
+        ::decode(source)
+        .into_vec()
+        .ok()
+        .and_then(|bytes| Keypair::from_bytes(&bytes).ok())
+        .ok_or_else(|| "Invalid keypair: malformed base58 bytes".to_string())
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Tip {
     pub time: String,
@@ -73,3 +174,18 @@ pub struct Tip {
     pub landed_tips_99th_percentile: f64,
     pub ema_landed_tips_50th_percentile: f64,
 }
+
+impl Tip {
+    /// Returns the landed tip at the given percentile, in SOL, or none if the tip stream does not
+    /// publish it.
+    pub fn percentile(&self, percentile: u8) -> Option<f64> {
+        match percentile {
+            25 => Some(self.landed_tips_25th_percentile),
+            50 => Some(self.landed_tips_50th_percentile),
+            75 => Some(self.landed_tips_75th_percentile),
+            95 => Some(self.landed_tips_95th_percentile),
+            99 => Some(self.landed_tips_99th_percentile),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures::{
+    stream::{select_all, BoxStream},
+    StreamExt,
+};
+use ore_api::state::Proof;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{nonblocking::pubsub_client::PubsubClient, rpc_config::RpcAccountInfoConfig};
+use solana_program::pubkey::Pubkey;
+
+use crate::{
+    utils::{amount_u64_to_f64, amount_u64_to_string},
+    Miner,
+};
+
+/// Clears the terminal and moves the cursor to the top left.
+const CLEAR_SCREEN: &str = "\x1B[2J\x1B[1;1H";
+
+/// An update to the data of the watched account at an index.
+type AccountUpdate = (usize, Option<Vec<u8>>);
+
+impl Miner {
+    /// Redraws `render` whenever the watched accounts change, and at least every `interval`.
+    /// Accounts are streamed over websocket subscriptions where the RPC supports them, so
+    /// watching costs no requests between updates, and polled otherwise.
+    pub async fn watch_accounts<F>(&self, addresses: &[Pubkey], interval: Duration, mut render: F)
+    where
+        F: FnMut(&[Option<Vec<u8>>]) -> String,
+    {
+        let mut accounts = self
+            .fetch_accounts(addresses)
+            .await
+            .unwrap_or_else(|| vec![None; addresses.len()]);
+        let pubsub = PubsubClient::new(&self.websocket_url()).await.ok();
+        let mut updates = match &pubsub {
+            Some(pubsub) => subscribe(pubsub, addresses).await,
+            None => None,
+        };
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            let source = match updates {
+                Some(_) => "Streaming account updates".to_string(),
+                None => format!("Polling every {}s", interval.as_secs()),
+            };
+            print!("{}{}", CLEAR_SCREEN, render(&accounts));
+            println!("\n{} (Ctrl+C to exit)", source);
+
+            tokio::select! {
+                update = next_update(&mut updates) => match update {
+                    Some((index, data)) => accounts[index] = data,
+                    // The subscription closed, so fall back to polling
+                    None => updates = None,
+                },
+                _ = ticker.tick() => {
+                    if updates.is_none() {
+                        // Keep showing the last known state if the fetch fails
+                        if let Some(fetched) = self.fetch_accounts(addresses).await {
+                            accounts = fetched;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fetches the data of each account, with none for accounts that do not exist. Returns none
+    /// if the request fails.
+    async fn fetch_accounts(&self, addresses: &[Pubkey]) -> Option<Vec<Option<Vec<u8>>>> {
+        let accounts = self
+            .rpc_client
+            .get_multiple_accounts(addresses)
+            .await
+            .ok()?;
+        Some(
+            accounts
+                .into_iter()
+                .map(|account| account.map(|account| account.data))
+                .collect(),
+        )
+    }
+
+    /// Returns the websocket endpoint of the best RPC endpoint.
+    fn websocket_url(&self) -> String {
+        let url = self.rpc_pool.ranked()[0].url();
+        solana_cli_config::Config::compute_websocket_url(&url)
+    }
+}
+
+/// Subscribes to every account, merging the updates into one stream. Returns none if any
+/// subscription fails.
+async fn subscribe<'a>(
+    pubsub: &'a PubsubClient,
+    addresses: &[Pubkey],
+) -> Option<BoxStream<'a, AccountUpdate>> {
+    let config = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        ..Default::default()
+    };
+    let mut streams = vec![];
+    for (index, address) in addresses.iter().enumerate() {
+        let (stream, _) = pubsub
+            .account_subscribe(address, Some(config.clone()))
+            .await
+            .ok()?;
+        streams.push(
+            stream
+                .map(move |response| {
+                    let data = response.value.data.decode().filter(|data| !data.is_empty());
+                    (index, data)
+                })
+                .boxed(),
+        );
+    }
+    Some(select_all(streams).boxed())
+}
+
+/// Waits for the next account update, or forever if there is no subscription.
+async fn next_update(updates: &mut Option<BoxStream<'_, AccountUpdate>>) -> Option<AccountUpdate> {
+    match updates {
+        Some(updates) => updates.next().await,
+        None => futures::future::pending().await,
+    }
+}
+
+/// Describes how a proof changed since watching started: the stake gained, the reward rate and
+/// the time since its last hash.
+pub fn proof_activity(start: &Proof, proof: &Proof, elapsed: Duration) -> String {
+    let rewards = proof.total_rewards.saturating_sub(start.total_rewards);
+    let hours = elapsed.as_secs_f64() / 3600.0;
+    let rate = if hours.gt(&0.0) {
+        format!("{:.4} ORE/h", amount_u64_to_f64(rewards) / hours)
+    } else {
+        "-".to_string()
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_secs() as i64);
+    format!(
+        "Stake change: {} ORE\nRewards: {} ORE\nReward rate: {}\nLast hash: {}s ago\n",
+        amount_change(start.balance, proof.balance),
+        amount_u64_to_string(rewards),
+        rate,
+        now.saturating_sub(proof.last_hash_at).max(0)
+    )
+}
+
+/// Formats the change between two amounts with its sign, e.g. `+1.5`.
+pub fn amount_change(start: u64, now: u64) -> String {
+    format!(
+        "{}{}",
+        if now.ge(&start) { "+" } else { "-" },
+        amount_u64_to_string(now.abs_diff(start))
+    )
+}